@@ -1,23 +1,48 @@
-const INIT: &[&'static str] = &["EOF", "byte", "input", "output"];
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{Loc, Warning},
+    parser::Fixity,
+};
+
+// `#int` cannot be written in source: there `#` starts a comment, or a numeral
+// before a digit, and a quoted symbol cannot start with it
+const INIT: &[&str] = &["EOF", "byte", "input", "output", "#int"];
 pub const EOF: Id = Id(0);
 pub const BYTE: Id = Id(1);
 pub const INPUT: Id = Id(2);
 pub const OUTPUT: Id = Id(3);
+// the head of an application of a native integer
+pub const INT: Id = Id(4);
 
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
 pub struct Id(usize);
 
 pub struct Alloc {
     ids: Vec<String>,
+    files: Vec<PathBuf>,
+    // the code of each file loaded, which the locations in it refer to
+    sources: HashMap<usize, String>,
+    // operators declared with `infixl`, `infixr` or `infix` in any file, for
+    // printing them infix; each file parses with its own declarations only
+    fixities: HashMap<Id, Fixity>,
+    warnings: Vec<Warning>,
 }
 
 impl Alloc {
     pub fn new() -> Self {
         Self {
-            ids: INIT.into_iter().map(ToString::to_string).collect(),
+            ids: INIT.iter().map(ToString::to_string).collect(),
+            files: Vec::new(),
+            sources: HashMap::new(),
+            fixities: HashMap::new(),
+            warnings: Vec::new(),
         }
     }
-    pub fn alloc_or_get<'a>(&mut self, s: &'a str) -> Id {
+    pub fn alloc_or_get(&mut self, s: &str) -> Id {
         match self.ids.iter().position(|x| x == s) {
             Some(x) => Id(x),
             None => {
@@ -26,9 +51,63 @@ impl Alloc {
             }
         }
     }
+    // an identifier that has not been used so far
+    pub fn fresh(&mut self, base: &str) -> Id {
+        let mut n = 0;
+        loop {
+            let s = format!("{base}_{n}");
+            if !self.ids.contains(&s) {
+                return self.alloc_or_get(&s);
+            }
+            n += 1;
+        }
+    }
+    // the name `module.name`, as written when referring to it from outside
+    pub fn qualify(&mut self, module: &Id, name: &Id) -> Id {
+        let s = format!("{}.{}", self.get_string(module), self.get_string(name));
+        self.alloc_or_get(&s)
+    }
+    // every identifier so far, internal ones included
+    pub(crate) fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.ids.iter().map(String::as_str)
+    }
     pub fn get_string(&self, i: &Id) -> &str {
         &self.ids[i.0]
     }
+    // file indices start at 1, so that `Loc`s outside any file can use 0
+    pub fn add_file(&mut self, path: &Path) -> usize {
+        self.files.push(path.to_path_buf());
+        self.files.len()
+    }
+    pub fn get_file(&self, file: usize) -> Option<&Path> {
+        file.checked_sub(1).map(|i| &*self.files[i])
+    }
+    pub(crate) fn add_source(&mut self, file: usize, src: &str) {
+        self.sources.insert(file, src.to_string());
+    }
+    pub(crate) fn get_source(&self, file: usize) -> Option<&str> {
+        self.sources.get(&file).map(String::as_str)
+    }
+    // the first file to declare `op` decides how it is printed
+    pub(crate) fn declare_fixity(&mut self, op: Id, fixity: Fixity) {
+        self.fixities.entry(op).or_insert(fixity);
+    }
+    pub(crate) fn fixity(&self, op: &Id) -> Option<Fixity> {
+        self.fixities.get(op).copied()
+    }
+    pub(crate) fn warn(&mut self, loc: Loc, desc: String) {
+        self.warnings.push(Warning { loc, desc });
+    }
+    // the warnings given since the last call, oldest first
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+impl Default for Alloc {
+    fn default() -> Self {
+        Alloc::new()
+    }
 }
 
 pub trait DisplayWithAlloc {
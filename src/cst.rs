@@ -0,0 +1,167 @@
+use std::fmt;
+
+use crate::{
+    alloc::Alloc,
+    error::{Error, Loc},
+    token::{Scanner, Token},
+};
+
+pub use crate::token::TokenTy;
+
+// a source file as scanned, down to its whitespace and comments: printing it
+// gives back the source, and parsing its tokens gives the definitions
+#[derive(Clone, Debug)]
+pub struct Cst {
+    pub nodes: Vec<Node>,
+    // holds the whitespace and comments after the last token
+    pub eof: SyntaxToken,
+}
+
+#[derive(Clone, Debug)]
+pub enum Node {
+    Token(SyntaxToken),
+    Group(Group),
+}
+
+// a bracketed part, `( ... )`, `[ ... ]` or `{ ... }`. `close` is `None` if
+// the file ends first
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub open: SyntaxToken,
+    pub nodes: Vec<Node>,
+    pub close: Option<SyntaxToken>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SyntaxToken {
+    // the whitespace and comments before the token
+    pub trivia: String,
+    pub text: String,
+    token: Token,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> &TokenTy {
+        self.token.kind()
+    }
+    pub fn loc(&self) -> Loc {
+        self.token.loc()
+    }
+}
+
+pub fn parse(alloc: &mut Alloc, src: &str, file: usize) -> Result<Cst, Error> {
+    let mut sc = Scanner::new(src).in_file(file);
+    // the groups being read, each with the nodes around it
+    let mut open: Vec<(SyntaxToken, Vec<Node>)> = Vec::new();
+    let mut nodes = Vec::new();
+    loop {
+        let token = sc.next_token(alloc)?;
+        let tok = SyntaxToken {
+            trivia: src[token.trivia.clone()].to_string(),
+            text: src[token.text.clone()].to_string(),
+            token,
+        };
+        use TokenTy::*;
+        let closes = |open: &SyntaxToken| {
+            matches!(
+                (open.kind(), tok.kind()),
+                (Lparen, Rparen) | (Lbracket, Rbracket) | (Lbrace, Rbrace)
+            )
+        };
+        match tok.kind() {
+            Eof => {
+                while let Some((group, outer)) = open.pop() {
+                    nodes = close(group, nodes, outer, None);
+                }
+                return Ok(Cst { nodes, eof: tok });
+            }
+            Lparen | Lbracket | Lbrace => open.push((tok, std::mem::take(&mut nodes))),
+            Rparen | Rbracket | Rbrace if open.last().is_some_and(|(o, _)| closes(o)) => {
+                let (group, outer) = open.pop().unwrap();
+                nodes = close(group, nodes, outer, Some(tok));
+            }
+            _ => nodes.push(Node::Token(tok)),
+        }
+    }
+}
+
+fn close(
+    open: SyntaxToken,
+    nodes: Vec<Node>,
+    mut outer: Vec<Node>,
+    close: Option<SyntaxToken>,
+) -> Vec<Node> {
+    outer.push(Node::Group(Group { open, nodes, close }));
+    outer
+}
+
+impl Cst {
+    // every token in source order, ending with `eof`
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut res = tokens(&self.nodes);
+        res.push(&self.eof);
+        res
+    }
+
+    // the tokens of the top-level item with a token at `loc`, up to its `;`
+    pub(crate) fn item(&self, loc: Loc) -> Option<Vec<&SyntaxToken>> {
+        let mut start = 0;
+        for (i, node) in self.nodes.iter().enumerate() {
+            if !matches!(node, Node::Token(tok) if *tok.kind() == TokenTy::Semi) {
+                continue;
+            }
+            let tokens = tokens(&self.nodes[start..=i]);
+            start = i + 1;
+            if tokens
+                .iter()
+                .any(|t| (t.loc().line, t.loc().col) == (loc.line, loc.col))
+            {
+                return Some(tokens);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn scanner(&self) -> Scanner<'static> {
+        Scanner::replay(self.tokens().into_iter().map(|t| t.token.clone()).collect())
+    }
+}
+
+// every token of `nodes` in source order
+pub fn tokens(nodes: &[Node]) -> Vec<&SyntaxToken> {
+    let mut res = Vec::new();
+    tokens_of(nodes, &mut res);
+    res
+}
+
+// the source of `tokens`, without what comes before the first
+pub(crate) fn text(tokens: &[&SyntaxToken]) -> String {
+    let mut s = tokens[0].text.clone();
+    for tok in &tokens[1..] {
+        s.push_str(&tok.trivia);
+        s.push_str(&tok.text);
+    }
+    s
+}
+
+fn tokens_of<'c>(nodes: &'c [Node], res: &mut Vec<&'c SyntaxToken>) {
+    for node in nodes {
+        match node {
+            Node::Token(tok) => res.push(tok),
+            Node::Group(group) => {
+                res.push(&group.open);
+                tokens_of(&group.nodes, res);
+                res.extend(&group.close);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for tok in self.tokens() {
+            write!(f, "{}{}", tok.trivia, tok.text)?;
+        }
+        Ok(())
+    }
+}
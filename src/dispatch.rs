@@ -0,0 +1,101 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    alloc::Alloc,
+    error::{Error, ErrorTy},
+    parser::{Def, Expr},
+    unify::{is_instance, rename, unify},
+    Rules,
+};
+
+// reorders the clause lists written only in `files` so that more specific
+// patterns come first, so the first matching clause `apply` finds is the most
+// specific one. fails if two patterns overlap without one being an instance
+// of the other
+pub(crate) fn order_by_specificity(
+    alloc: &mut Alloc,
+    defs: &mut Rules,
+    files: &HashSet<usize>,
+) -> Result<(), Error> {
+    let mut heads: Vec<_> = defs
+        .values_mut()
+        .filter(|d| d.iter().all(|def| files.contains(&def.loc.file)))
+        .collect();
+    heads.sort_by_key(|d| (d[0].loc.file, d[0].loc.line, d[0].loc.col));
+    for clauses in heads {
+        check_comparable(alloc, clauses)?;
+
+        let mut rest = std::mem::take(clauses);
+        while !rest.is_empty() {
+            let i = (0..rest.len())
+                .find(|&i| !rest.iter().any(|d| more_specific(d, &rest[i])))
+                .unwrap();
+            clauses.push(rest.remove(i));
+        }
+    }
+    Ok(())
+}
+
+// of two equally specific patterns, the one with guards left goes first
+fn more_specific(a: &Def, b: &Def) -> bool {
+    let ((a_pat, a_guarded), (b_pat, b_guarded)) = (refined(a), refined(b));
+    match (is_instance(&a_pat, &b_pat), is_instance(&b_pat, &a_pat)) {
+        (true, false) => true,
+        (true, true) => a_guarded && !b_guarded,
+        _ => false,
+    }
+}
+
+// the pattern of `def` with every guard `v => p` on one of its variables
+// folded back in, which is what an as-pattern `v@p` became, and whether any
+// other guards are left
+fn refined(def: &Def) -> (Expr, bool) {
+    let mut pat = def.pat.clone();
+    let mut guarded = false;
+    for (guard, nf) in &def.guards {
+        let mut vars = HashSet::new();
+        crate::vars(&mut vars, &pat);
+        match guard {
+            Expr::Var { id, .. } if vars.contains(id) => match unify(guard, nf) {
+                Some(s) => pat = s.apply(&pat),
+                None => guarded = true,
+            },
+            _ => guarded = true,
+        }
+    }
+    (pat, guarded)
+}
+
+fn check_comparable(alloc: &mut Alloc, clauses: &[Def]) -> Result<(), Error> {
+    let pats: Vec<_> = clauses.iter().map(|def| refined(def).0).collect();
+    for (j, b) in clauses.iter().enumerate() {
+        // rename apart so that shared variable names do not constrain the overlap
+        let mut vars = HashSet::new();
+        crate::vars(&mut vars, &pats[j]);
+        let map: HashMap<_, _> = vars
+            .into_iter()
+            .map(|id| {
+                let name = format!("{};", alloc.get_string(&id));
+                (id, alloc.alloc_or_get(&name))
+            })
+            .collect();
+        let pat = rename(&map, &pats[j]);
+
+        for (i, a) in clauses[..j].iter().enumerate() {
+            if unify(&pats[i], &pat).is_some()
+                && !is_instance(&pats[i], &pats[j])
+                && !is_instance(&pats[j], &pats[i])
+            {
+                return Err(Error {
+                    loc: b.loc,
+                    ty: ErrorTy::DispatchError,
+                    desc: format!(
+                        "this rule overlaps with the one at {}, but neither is more specific",
+                        a.loc.describe(alloc)
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::{
+    alloc::Alloc,
+    cst::{self, Cst, SyntaxToken, TokenTy},
+    graph::CallGraph,
+    parser::{AttrArg, Def, Expr},
+    Rules,
+};
+
+// what the reference says about one head symbol
+struct Entry<'r> {
+    name: &'r str,
+    defs: &'r [Def],
+    arities: Vec<usize>,
+    docs: Vec<&'r str>,
+    deprecated: Option<&'r str>,
+    calls: Vec<&'r str>,
+    called_by: Vec<&'r str>,
+}
+
+// the functions of the call graph of `rules`, by file and in source order
+fn entries<'r>(alloc: &'r Alloc, rules: &'r Rules) -> Vec<(String, Vec<Entry<'r>>)> {
+    let graph = CallGraph::new(alloc, rules);
+    let mut entries: Vec<Entry> = Vec::new();
+    for id in &graph.funs {
+        let (name, defs) = (alloc.get_string(id), &rules[id][..]);
+        let mut arities: Vec<_> = defs.iter().map(|def| arity(&def.pat)).collect();
+        arities.sort();
+        arities.dedup();
+        let mut docs = Vec::new();
+        let mut deprecated = None;
+        for attr in defs.iter().flat_map(|def| &def.attrs) {
+            let arg = match &attr.args[..] {
+                [AttrArg::Str(s)] => Some(&s[..]),
+                _ => None,
+            };
+            match alloc.get_string(&attr.name) {
+                "doc" => docs.extend(arg.filter(|s| !docs.contains(s))),
+                "deprecated" => deprecated = Some(arg.unwrap_or("")),
+                _ => {}
+            }
+        }
+        entries.push(Entry {
+            name,
+            defs,
+            arities,
+            docs,
+            deprecated,
+            calls: Vec::new(),
+            called_by: Vec::new(),
+        });
+    }
+    for (i, callees) in graph.calls.iter().enumerate() {
+        for &j in callees.iter().filter(|&&j| j != i) {
+            let (caller, callee) = (entries[i].name, entries[j].name);
+            entries[i].calls.push(callee);
+            entries[j].called_by.push(caller);
+        }
+    }
+
+    let mut files: Vec<(String, Vec<Entry>)> = Vec::new();
+    let mut last = None;
+    for entry in entries {
+        let file = entry.defs[0].loc.file;
+        if last != Some(file) {
+            let path = alloc
+                .get_file(file)
+                .map_or("<input>".into(), |p| p.display().to_string());
+            files.push((path, Vec::new()));
+            last = Some(file);
+        }
+        files.last_mut().unwrap().1.push(entry);
+    }
+    files
+}
+
+// the number of arguments a pattern takes
+fn arity(pat: &Expr) -> usize {
+    pat.as_app().map_or(0, |app| 1 + arity(&app.f))
+}
+
+fn arity_text(arities: &[usize]) -> String {
+    let list: Vec<_> = arities.iter().map(usize::to_string).collect();
+    match list.len() {
+        1 => format!("arity {}", list[0]),
+        _ => format!("arities {}", list.join(", ")),
+    }
+}
+
+// an `id` for the entry of `name` that HTML and Markdown both accept
+fn anchor(name: &str) -> String {
+    let mut res = String::from("f-");
+    for c in name.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' => res.push(c),
+            c => write!(res, "_{:x}", c as u32).unwrap(),
+        }
+    }
+    res
+}
+
+fn escape(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        match c {
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '&' => res.push_str("&amp;"),
+            '"' => res.push_str("&quot;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+// the parsed code of each file with an entry
+fn sources(alloc: &Alloc, files: &[(String, Vec<Entry>)]) -> HashMap<usize, Cst> {
+    let mut res = HashMap::new();
+    for (_, entries) in files {
+        let file = entries[0].defs[0].loc.file;
+        let cst = alloc
+            .get_source(file)
+            .and_then(|src| cst::parse(&mut Alloc::new(), src, file).ok());
+        res.extend(cst.map(|cst| (file, cst)));
+    }
+    res
+}
+
+// the definitions of an entry as written, so with their `where` blocks and
+// anonymous functions rather than the functions lifted out of them
+fn clauses<'c>(sources: &'c HashMap<usize, Cst>, defs: &[Def]) -> Vec<Vec<&'c SyntaxToken>> {
+    let mut res: Vec<Vec<&SyntaxToken>> = Vec::new();
+    for def in defs {
+        let item = sources.get(&def.loc.file).and_then(|cst| cst.item(def.loc));
+        if let Some(item) = item {
+            if !res.iter().any(|i| std::ptr::eq(i[0], item[0])) {
+                res.push(item);
+            }
+        }
+    }
+    res
+}
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: auto; }
+pre { background: #f6f6f6; padding: 0.5em; overflow-x: auto; }
+.fun { color: #1f5fa8; }
+.var { color: #8a3f9e; }
+.num { color: #a05a00; }
+.str { color: #2a7a2a; }
+.key { font-weight: bold; }
+.meta { color: #666; }
+.deprecated { color: #a00; }
+";
+
+// a self-contained HTML page with an entry per function
+pub fn html(alloc: &Alloc, rules: &Rules, title: &str) -> String {
+    let files = entries(alloc, rules);
+    let sources = sources(alloc, &files);
+    let kinds = kinds(alloc, rules);
+    let links: Vec<&str> = files
+        .iter()
+        .flat_map(|(_, es)| es.iter().map(|e| e.name))
+        .collect();
+    let link = |name: &str| {
+        format!(
+            "<a href=\"#{}\"><code>{}</code></a>",
+            anchor(name),
+            escape(name)
+        )
+    };
+
+    let mut s = String::new();
+    writeln!(
+        s,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<title>{}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>",
+        escape(title)
+    )
+    .unwrap();
+    writeln!(s, "<h1>{}</h1>\n<nav><ul>", escape(title)).unwrap();
+    for name in &links {
+        writeln!(s, "<li>{}</li>", link(name)).unwrap();
+    }
+    writeln!(s, "</ul></nav>").unwrap();
+    for (path, entries) in &files {
+        writeln!(s, "<h2>{}</h2>", escape(path)).unwrap();
+        for e in entries {
+            writeln!(s, "<section id=\"{}\">", anchor(e.name)).unwrap();
+            writeln!(s, "<h3><code>{}</code></h3>", escape(e.name)).unwrap();
+            writeln!(s, "<p class=\"meta\">{}</p>", arity_text(&e.arities)).unwrap();
+            if let Some(note) = e.deprecated {
+                let note = if note.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", escape(note))
+                };
+                writeln!(s, "<p class=\"deprecated\">deprecated{note}</p>").unwrap();
+            }
+            for doc in &e.docs {
+                for para in doc.split("\n\n") {
+                    writeln!(s, "<p>{}</p>", escape(para)).unwrap();
+                }
+            }
+            s.push_str("<pre><code>");
+            for (i, clause) in clauses(&sources, e.defs).iter().enumerate() {
+                if i > 0 {
+                    s.push('\n');
+                }
+                highlight(clause, &kinds, &links, &mut s);
+            }
+            s.push_str("</code></pre>\n");
+            for (label, names) in [("calls", &e.calls), ("called by", &e.called_by)] {
+                if !names.is_empty() {
+                    let names: Vec<_> = names.iter().map(|n| link(n)).collect();
+                    writeln!(s, "<p class=\"meta\">{label}: {}</p>", names.join(", ")).unwrap();
+                }
+            }
+            writeln!(s, "</section>").unwrap();
+        }
+    }
+    s.push_str("</body>\n</html>\n");
+    s
+}
+
+// what the identifier at a location stands for
+enum Kind<'r> {
+    Fun(&'r str),
+    Var,
+    Num,
+}
+
+// the kind of every symbol in the rules, by file, line and column
+fn kinds<'r>(alloc: &'r Alloc, rules: &Rules) -> HashMap<(usize, u64, u64), Kind<'r>> {
+    let mut res = HashMap::new();
+    for def in rules.values().flatten() {
+        // outside the pattern, a symbol bound as a variable is one
+        let mut bound = HashSet::new();
+        crate::vars(&mut bound, &def.pat);
+        for (_, nf) in &def.guards {
+            crate::vars(&mut bound, nf);
+        }
+        let exprs = [&def.pat, &def.rep]
+            .into_iter()
+            .chain(def.guards.iter().flat_map(|(g, nf)| [g, nf]));
+        for e in exprs {
+            symbol_kinds(alloc, e, &bound, &mut res);
+        }
+    }
+    res
+}
+
+fn symbol_kinds<'r>(
+    alloc: &'r Alloc,
+    e: &Expr,
+    bound: &HashSet<crate::Id>,
+    res: &mut HashMap<(usize, u64, u64), Kind<'r>>,
+) {
+    crate::with_stacker(|| {
+        let kind = match e {
+            Expr::Fun { id, .. } if bound.contains(id) => Kind::Var,
+            Expr::Fun { id, .. } => Kind::Fun(alloc.get_string(id)),
+            Expr::Var { .. } => Kind::Var,
+            Expr::Int { .. } => Kind::Num,
+            _ => {
+                let app = e.as_app().unwrap();
+                symbol_kinds(alloc, &app.f, bound, res);
+                return symbol_kinds(alloc, &app.arg, bound, res);
+            }
+        };
+        let loc = e.loc();
+        res.entry((loc.file, loc.line, loc.col)).or_insert(kind);
+    })
+}
+
+// the tokens of a definition as written, with functions that have an entry
+// linked
+fn highlight(
+    tokens: &[&SyntaxToken],
+    kinds: &HashMap<(usize, u64, u64), Kind>,
+    links: &[&str],
+    s: &mut String,
+) {
+    for (i, tok) in tokens.iter().enumerate() {
+        if i > 0 {
+            s.push_str(&escape(&tok.trivia));
+        }
+        let loc = tok.loc();
+        let text = escape(&tok.text);
+        let class = match (tok.kind(), kinds.get(&(loc.file, loc.line, loc.col))) {
+            (TokenTy::Ident(_), Some(Kind::Fun(name))) if links.contains(name) => {
+                write!(s, "<a class=\"fun\" href=\"#{}\">{text}</a>", anchor(name)).unwrap();
+                continue;
+            }
+            (TokenTy::Ident(_), Some(Kind::Fun(_))) => "fun",
+            (TokenTy::Ident(_), Some(Kind::Var)) => "var",
+            (TokenTy::Num(_), _) => "num",
+            (TokenTy::Str(_) | TokenTy::Char(_), _) => "str",
+            (TokenTy::Equal | TokenTy::Arrow, _) => "key",
+            (TokenTy::Ident(_), _) if KEYWORDS.contains(&&tok.text[..]) => "key",
+            (TokenTy::Ident(_), _) if tok.text.starts_with('@') => "meta",
+            _ => {
+                s.push_str(&text);
+                continue;
+            }
+        };
+        write!(s, "<span class=\"{class}\">{text}</span>").unwrap();
+    }
+}
+
+const KEYWORDS: &[&str] = &["if", "where", "let", "in", "private"];
+
+// the same reference as Markdown, with the clauses in `rhokell` code blocks
+pub fn markdown(alloc: &Alloc, rules: &Rules, title: &str) -> String {
+    let files = entries(alloc, rules);
+    let sources = sources(alloc, &files);
+    let link = |name: &str| format!("[{}](#{})", code_span(name), anchor(name));
+
+    let mut s = format!("# {}\n", escape_markdown(title));
+    for (path, entries) in &files {
+        write!(s, "\n## {}\n", code_span(path)).unwrap();
+        for e in entries {
+            write!(
+                s,
+                "\n<a id=\"{}\"></a>\n\n### {}\n\n",
+                anchor(e.name),
+                code_span(e.name)
+            )
+            .unwrap();
+            writeln!(s, "*{}*", arity_text(&e.arities)).unwrap();
+            if let Some(note) = e.deprecated {
+                let note = if note.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {note}")
+                };
+                write!(s, "\n**deprecated{note}**\n").unwrap();
+            }
+            for doc in &e.docs {
+                write!(s, "\n{doc}\n").unwrap();
+            }
+            let code: Vec<_> = clauses(&sources, e.defs)
+                .iter()
+                .map(|clause| cst::text(clause))
+                .collect();
+            let code = code.join("\n");
+            let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+            write!(s, "\n{fence}rhokell\n{code}\n{fence}\n").unwrap();
+            for (label, names) in [("calls", &e.calls), ("called by", &e.called_by)] {
+                if !names.is_empty() {
+                    let names: Vec<_> = names.iter().map(|n| link(n)).collect();
+                    write!(s, "\n{label}: {}\n", names.join(", ")).unwrap();
+                }
+            }
+        }
+    }
+    s
+}
+
+fn longest_run(s: &str, c: char) -> usize {
+    s.split(|d| d != c).map(str::len).max().unwrap_or(0)
+}
+
+// a Markdown code span showing `s` verbatim, even with backticks in it
+fn code_span(s: &str) -> String {
+    let fence = "`".repeat(longest_run(s, '`') + 1);
+    let pad = if s.starts_with('`') || s.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{fence}{pad}{s}{pad}{fence}")
+}
+
+// `s` as Markdown text, with no character taken as markup
+fn escape_markdown(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        if c.is_ascii_punctuation() {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
@@ -1,5 +1,7 @@
 use std::fmt::*;
 
+use crate::alloc::Alloc;
+
 #[derive(Clone, Debug)]
 pub struct Error {
     pub loc: Loc,
@@ -8,8 +10,21 @@ pub struct Error {
 }
 
 impl Error {
-    pub fn report(&self) {
-        eprintln!("{} @ {}: {}", self.ty, self.loc, self.desc);
+    pub fn report(&self, alloc: &Alloc) {
+        eprintln!("{} @ {}: {}", self.ty, self.loc.describe(alloc), self.desc);
+    }
+}
+
+// something suspicious that does not stop the program from loading
+#[derive(Clone, Debug)]
+pub struct Warning {
+    pub loc: Loc,
+    pub desc: String,
+}
+
+impl Warning {
+    pub fn report(&self, alloc: &Alloc) {
+        eprintln!("warning @ {}: {}", self.loc.describe(alloc), self.desc);
     }
 }
 
@@ -17,11 +32,17 @@ impl Error {
 pub struct Loc {
     pub line: u64,
     pub col: u64,
+    // index into `Alloc`'s files, 0 if the source did not come from a file
+    pub file: usize,
 }
 
 impl Loc {
     pub fn new() -> Self {
-        Self { line: 1, col: 1 }
+        Self {
+            line: 1,
+            col: 1,
+            file: 0,
+        }
     }
     pub fn new_line(&mut self) {
         self.line += 1;
@@ -30,6 +51,13 @@ impl Loc {
     pub fn col(&mut self) {
         self.col += 1;
     }
+    // like `Display`, but naming the file the location is in, if any
+    pub fn describe(&self, alloc: &Alloc) -> String {
+        match alloc.get_file(self.file) {
+            Some(path) => format!("{}, {self}", path.display()),
+            None => self.to_string(),
+        }
+    }
 }
 
 impl Display for Loc {
@@ -45,9 +73,15 @@ impl Default for Loc {
 }
 
 #[derive(Clone, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum ErrorTy {
     SyntaxError,
     CExprError,
+    DispatchError,
+    ImportError,
+    ScopeError,
+    ManifestError,
+    MacroError,
 }
 
 impl Display for ErrorTy {
@@ -56,6 +90,11 @@ impl Display for ErrorTy {
         match self {
             SyntaxError => write!(fmt, "syntax error"),
             CExprError => write!(fmt, "closedness check error"),
+            DispatchError => write!(fmt, "dispatch error"),
+            ImportError => write!(fmt, "import error"),
+            MacroError => write!(fmt, "macro expansion error"),
+            ScopeError => write!(fmt, "scope error"),
+            ManifestError => write!(fmt, "manifest error"),
         }
     }
 }
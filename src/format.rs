@@ -0,0 +1,440 @@
+use crate::{
+    alloc::Alloc,
+    cst::{self, Group, Node, SyntaxToken, TokenTy},
+    error::Error,
+};
+
+const WIDTH: usize = 100;
+const INDENT: usize = 4;
+
+// `src` with one space between tokens, the `=` of consecutive one-line
+// definitions aligned, items too long for a line broken at their `=` and
+// their groups, comments kept and blank lines between items kept but never
+// more than one. only whitespace changes
+pub fn format(alloc: &mut Alloc, src: &str, file: usize) -> Result<String, Error> {
+    let cst = cst::parse(alloc, src, file)?;
+    let mut p = Printer::default();
+    p.items(&cst.nodes, 0, &cst.eof);
+    let mut out = p.out.trim_end().to_string();
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+struct Comment<'t> {
+    text: &'t str,
+    // whether a line, or a blank line, separates it from what comes before
+    newline: bool,
+    blank: bool,
+}
+
+// the comments in `trivia`, and whether a blank line follows the last of
+// them, or starts `trivia` if there are none
+fn comments(trivia: &str) -> (Vec<Comment<'_>>, bool) {
+    let mut res = Vec::new();
+    let mut rest = trivia;
+    loop {
+        let text = rest.trim_start();
+        let newlines = rest[..rest.len() - text.len()].matches('\n').count();
+        rest = text;
+        if rest.is_empty() {
+            return (res, newlines > 1);
+        }
+        let len = if rest.starts_with("#|") {
+            block_comment_len(rest)
+        } else {
+            rest.find('\n').unwrap_or(rest.len())
+        };
+        res.push(Comment {
+            text: rest[..len].trim_end(),
+            newline: newlines > 0,
+            blank: newlines > 1,
+        });
+        rest = &rest[len..];
+    }
+}
+
+// the length of the `#| ... |#` `s` starts with, which the scanner has
+// checked is closed
+fn block_comment_len(s: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    loop {
+        if s[i..].starts_with("#|") {
+            depth += 1;
+            i += 2;
+        } else if s[i..].starts_with("|#") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += s[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+}
+
+fn has_comments(tok: &SyntaxToken) -> bool {
+    !comments(&tok.trivia).0.is_empty()
+}
+
+fn needs_space(prev: &str, next: &str) -> bool {
+    let as_var = prev.len() > 1 && prev.ends_with('@');
+    !matches!(prev, "(" | "[") && !matches!(next, ")" | "]" | ";") && !as_var
+}
+
+// the tokens of `nodes` on one line, unless that is wider than `room` or a
+// comment other than one before `skip` is among them
+fn flat(nodes: &[Node], skip: Option<&SyntaxToken>, room: usize) -> Option<String> {
+    let mut s = String::new();
+    flat_into(nodes, skip, room, &mut s, &mut None).then_some(s)
+}
+
+fn flat_into<'c>(
+    nodes: &'c [Node],
+    skip: Option<&SyntaxToken>,
+    room: usize,
+    s: &mut String,
+    prev: &mut Option<&'c str>,
+) -> bool {
+    nodes.iter().all(|node| match node {
+        Node::Token(tok) => flat_token(tok, skip, room, s, prev),
+        Node::Group(group) => {
+            flat_token(&group.open, skip, room, s, prev)
+                && flat_into(&group.nodes, skip, room, s, prev)
+                && (group.close.iter()).all(|close| flat_token(close, skip, room, s, prev))
+        }
+    })
+}
+
+fn flat_token<'c>(
+    tok: &'c SyntaxToken,
+    skip: Option<&SyntaxToken>,
+    room: usize,
+    s: &mut String,
+    prev: &mut Option<&'c str>,
+) -> bool {
+    if has_comments(tok) && !skip.is_some_and(|t| std::ptr::eq(t, tok)) {
+        return false;
+    }
+    if prev.is_some_and(|p| needs_space(p, &tok.text)) {
+        s.push(' ');
+    }
+    s.push_str(&tok.text);
+    *prev = Some(&tok.text);
+    width(s) <= room
+}
+
+fn width(s: &str) -> usize {
+    s.chars().count()
+}
+
+fn is_ident(node: &Node, pred: impl Fn(&str) -> bool) -> bool {
+    matches!(node, Node::Token(tok) if matches!(tok.kind(), TokenTy::Ident(_)) && pred(&tok.text))
+}
+
+fn is_attr(node: &Node) -> bool {
+    is_ident(node, |s| s.len() > 1 && s.starts_with('@'))
+}
+
+fn is_token(node: &Node, ty: &TokenTy) -> bool {
+    matches!(node, Node::Token(tok) if tok.kind() == ty)
+}
+
+// the nodes up to and including each `;`
+fn split_items(nodes: &[Node]) -> Vec<&[Node]> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    for (i, node) in nodes.iter().enumerate() {
+        if is_token(node, &TokenTy::Semi) {
+            items.push(&nodes[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < nodes.len() {
+        items.push(&nodes[start..]);
+    }
+    items
+}
+
+fn first_token(nodes: &[Node]) -> &SyntaxToken {
+    match &nodes[0] {
+        Node::Token(tok) => tok,
+        Node::Group(group) => &group.open,
+    }
+}
+
+// an item that fits on a line as its left-hand side and the rest from `=`
+fn one_line(item: &[Node], ind: usize) -> Option<(String, String)> {
+    if is_attr(&item[0]) {
+        return None;
+    }
+    let eq = item.iter().position(|n| is_token(n, &TokenTy::Equal))?;
+    let room = WIDTH.saturating_sub(ind);
+    let lhs = flat(&item[..eq], Some(first_token(item)), room)?;
+    let rest = flat(&item[eq..], None, room)?;
+    (ind + width(&lhs) + 1 + width(&rest) <= WIDTH).then_some((lhs, rest))
+}
+
+// comments or a blank line before the item
+fn separated(item: &[Node]) -> bool {
+    let (comments, blank) = comments(&first_token(item).trivia);
+    !comments.is_empty() || blank
+}
+
+#[derive(Default)]
+struct Printer<'c> {
+    out: String,
+    line_start: usize,
+    prev: Option<&'c str>,
+    // a token whose comments have been written already
+    written: Option<&'c SyntaxToken>,
+}
+
+impl<'c> Printer<'c> {
+    fn col(&self) -> usize {
+        width(&self.out[self.line_start..])
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out[self.line_start..].trim().is_empty()
+    }
+
+    fn line_indent(&self) -> usize {
+        let line = &self.out[self.line_start..];
+        line.len() - line.trim_start().len()
+    }
+
+    fn newline(&mut self, ind: usize) {
+        let len = self.out.trim_end_matches(' ').len();
+        self.out.truncate(len);
+        self.out.push('\n');
+        self.line_start = self.out.len();
+        self.out.extend(std::iter::repeat_n(' ', ind));
+        self.prev = None;
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+        if let Some(i) = text.rfind('\n') {
+            self.line_start = self.out.len() - (text.len() - i - 1);
+        }
+    }
+
+    fn space_before(&mut self, next: &str) {
+        if !self.at_line_start() && self.prev.is_some_and(|p| needs_space(p, next)) {
+            self.out.push(' ');
+        }
+    }
+
+    fn token(&mut self, tok: &'c SyntaxToken, ind: usize) {
+        if !self.written.is_some_and(|t| std::ptr::eq(t, tok)) {
+            let (comments, _) = comments(&tok.trivia);
+            for c in &comments {
+                if !self.at_line_start() {
+                    self.newline(ind);
+                }
+                self.push(c.text);
+            }
+            if !comments.is_empty() {
+                self.newline(ind);
+            }
+        }
+        self.space_before(&tok.text);
+        self.push(&tok.text);
+        self.prev = Some(&tok.text);
+    }
+
+    // `node` on the current line if it fits there, or else on a line of its
+    // own at `wrap` if it fits there, or else broken over lines from where it
+    // is, continuing at `cont`
+    fn node(&mut self, node: &'c Node, wrap: usize, cont: usize) {
+        let nodes = std::slice::from_ref(node);
+        let space = usize::from(!self.at_line_start());
+        if let Some(s) = flat(
+            nodes,
+            self.written,
+            WIDTH.saturating_sub(self.col() + space),
+        ) {
+            return self.put_flat(nodes, &s);
+        }
+        // a token written without a space before it stays on the line
+        let glued = match node {
+            Node::Token(tok) => self.prev.is_some_and(|p| !needs_space(p, &tok.text)),
+            Node::Group(g) => *g.open.kind() == TokenTy::Lbrace,
+        };
+        let braces = matches!(node, Node::Group(g) if *g.open.kind() == TokenTy::Lbrace);
+        if !self.at_line_start() && wrap < self.col() && !glued {
+            if let Some(s) = flat(nodes, self.written, WIDTH.saturating_sub(wrap)) {
+                self.newline(wrap);
+                return self.put_flat(nodes, &s);
+            }
+        }
+        match node {
+            Node::Token(tok) => self.token(tok, wrap),
+            Node::Group(group) => {
+                // the bracket and the head go together
+                let head = group.nodes.first().map(std::slice::from_ref);
+                let room = WIDTH.saturating_sub(self.col() + space + 1);
+                let fits = head.is_none_or(|h| flat(h, None, room).is_some());
+                if !fits && !self.at_line_start() && wrap < self.col() && !braces {
+                    self.newline(wrap);
+                }
+                self.group(group, wrap, cont)
+            }
+        }
+    }
+
+    fn put_flat(&mut self, nodes: &'c [Node], s: &str) {
+        let tokens = cst::tokens(nodes);
+        self.space_before(&tokens[0].text);
+        self.push(s);
+        self.prev = Some(&tokens[tokens.len() - 1].text);
+    }
+
+    fn seq(&mut self, nodes: &'c [Node], wrap: usize, cont: usize) {
+        for node in nodes {
+            self.node(node, wrap, cont);
+        }
+    }
+
+    // a group that does not fit on a line: as many of its parts on each line
+    // as fit, the lines after the first at `cont`. the last part continues at
+    // `cont` too, so that nesting in the last argument, as in a long list,
+    // does not indent further. the definitions in braces are laid out like
+    // those of a file
+    fn group(&mut self, group: &'c Group, wrap: usize, cont: usize) {
+        self.token(&group.open, wrap);
+        let Some(close) = &group.close else {
+            return self.seq(&group.nodes, cont, cont);
+        };
+        if *group.open.kind() == TokenTy::Lbrace {
+            let outer = self.line_indent();
+            self.items(&group.nodes, outer + INDENT, close);
+            self.newline(outer);
+            return self.token(close, outer);
+        }
+        for (i, node) in group.nodes.iter().enumerate() {
+            let inner = match i + 1 == group.nodes.len() {
+                true => cont,
+                false => cont + 2,
+            };
+            self.node(node, cont, inner);
+        }
+        self.token(close, cont);
+    }
+
+    // the comments before an item, or before the end of a file or of a block,
+    // each on its own line but for one continuing the line before. the end
+    // itself is placed by the caller
+    fn leading(&mut self, tok: &'c SyntaxToken, ind: usize, first: &mut bool) {
+        let (comments, blank) = comments(&tok.trivia);
+        for c in &comments {
+            let starts_file = *first && self.out.is_empty();
+            if !(c.newline || starts_file) {
+                self.out.push(' ');
+                self.push(c.text);
+                *first = false;
+                continue;
+            }
+            self.separate(ind, *first, c.blank);
+            self.push(c.text);
+            *first = false;
+        }
+        if !matches!(tok.kind(), TokenTy::Eof | TokenTy::Rbrace) {
+            self.separate(ind, *first, blank);
+        }
+        *first = false;
+        self.written = Some(tok);
+    }
+
+    fn separate(&mut self, ind: usize, first: bool, blank: bool) {
+        if first && self.out.is_empty() {
+            return;
+        }
+        if blank && !first {
+            self.newline(ind);
+        }
+        self.newline(ind);
+    }
+
+    fn items(&mut self, nodes: &'c [Node], ind: usize, end: &'c SyntaxToken) {
+        let items = split_items(nodes);
+        let mut first = true;
+        let mut i = 0;
+        while i < items.len() {
+            let mut j = i;
+            let mut eq_col = 0;
+            while j < items.len() && (j == i || !separated(items[j])) {
+                let Some((lhs, _)) = one_line(items[j], ind) else {
+                    break;
+                };
+                eq_col = eq_col.max(width(&lhs));
+                j += 1;
+            }
+            if j == i {
+                self.item(items[i], ind, &mut first);
+                i += 1;
+                continue;
+            }
+            for item in &items[i..j] {
+                self.leading(first_token(item), ind, &mut first);
+                let (lhs, rest) = one_line(item, ind).unwrap();
+                self.push(&lhs);
+                let pad = match ind + eq_col + 1 + width(&rest) <= WIDTH {
+                    true => eq_col - width(&lhs),
+                    false => 0,
+                };
+                self.out.extend(std::iter::repeat_n(' ', pad + 1));
+                self.push(&rest);
+                self.prev = Some(";");
+            }
+            i = j;
+        }
+        self.leading(end, ind, &mut first);
+    }
+
+    fn item(&mut self, item: &'c [Node], ind: usize, first: &mut bool) {
+        self.leading(first_token(item), ind, first);
+        let mut k = 0;
+        while k < item.len() && is_attr(&item[k]) {
+            if k > 0 {
+                self.newline(ind);
+            }
+            self.node(&item[k], ind, ind);
+            k += 1;
+            while k < item.len()
+                && matches!(&item[k], Node::Token(t) if matches!(t.kind(), TokenTy::Str(_) | TokenTy::Num(_)))
+            {
+                self.node(&item[k], ind, ind);
+                k += 1;
+            }
+        }
+        if k > 0 && k < item.len() {
+            self.newline(ind);
+        }
+        let rest = &item[k..];
+        let Some(eq) = rest.iter().position(|n| is_token(n, &TokenTy::Equal)) else {
+            return self.seq(rest, ind + INDENT, ind + INDENT);
+        };
+        if flat(rest, self.written, WIDTH.saturating_sub(self.col())).is_some() {
+            return self.seq(rest, ind, ind);
+        }
+        self.seq(&rest[..=eq], ind + INDENT, ind + INDENT);
+        let rhs = &rest[eq + 1..];
+        if flat(rhs, None, WIDTH.saturating_sub(self.col() + 1)).is_some() {
+            return self.seq(rhs, ind, ind);
+        }
+        // the replacement, each guard and the `where` block on lines of
+        // their own
+        for (i, node) in rhs.iter().enumerate() {
+            let clause = is_ident(node, |s| s == "if" || s == "where");
+            if i == 0 || clause {
+                self.newline(ind + INDENT);
+            }
+            self.node(node, ind + INDENT, ind + INDENT + 2);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{
+    alloc::{Alloc, Id},
+    error::Loc,
+    json::Json,
+    loader, Rules,
+};
+
+// which functions of a program call which: an edge for every head symbol on
+// the replacement side of a function's definitions. functions lifted out of
+// another count as part of it, and those of the bundled modules are left out
+#[derive(Clone, Debug)]
+pub struct CallGraph {
+    // in source order
+    pub funs: Vec<Id>,
+    pub names: Vec<String>,
+    // indices into `funs`, ascending
+    pub calls: Vec<Vec<usize>>,
+}
+
+impl CallGraph {
+    pub fn new(alloc: &Alloc, rules: &Rules) -> CallGraph {
+        let mut funs: Vec<(Loc, &Id)> = Vec::new();
+        let mut uses: HashMap<&str, Vec<(Id, Loc)>> = HashMap::new();
+        for (id, defs) in rules {
+            let name = alloc.get_string(id);
+            let owner = name.split(';').next().unwrap();
+            let out = uses.entry(owner).or_default();
+            for def in defs {
+                loader::fun_uses(out, &def.rep);
+                for (guard, nf) in &def.guards {
+                    loader::fun_uses(out, guard);
+                    loader::fun_uses(out, nf);
+                }
+            }
+            if !name.contains(';') && !defs.is_empty() && !bundled(alloc, defs[0].loc) {
+                funs.push((defs[0].loc, id));
+            }
+        }
+        funs.sort_by_key(|(loc, _)| (loc.file, loc.line, loc.col));
+        let funs: Vec<Id> = funs.into_iter().map(|(_, id)| id.clone()).collect();
+        let index: HashMap<&Id, usize> = funs.iter().enumerate().map(|(i, id)| (id, i)).collect();
+        let names: Vec<String> = funs.iter().map(|id| alloc.get_string(id).into()).collect();
+        let calls = names
+            .iter()
+            .map(|name| {
+                let mut callees: Vec<usize> = uses[&name[..]]
+                    .iter()
+                    .filter_map(|(id, _)| index.get(id).copied())
+                    .collect();
+                callees.sort();
+                callees.dedup();
+                callees
+            })
+            .collect();
+        CallGraph { funs, names, calls }
+    }
+
+    // the strongly connected components with more than one function, that
+    // is the groups of mutually recursive ones, each in source order
+    pub fn mutual_recursion(&self) -> Vec<Vec<usize>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: vec![None; self.funs.len()],
+            low: vec![0; self.funs.len()],
+            stack: Vec::new(),
+            on_stack: vec![false; self.funs.len()],
+            next: 0,
+            sccs: Vec::new(),
+        };
+        for v in 0..self.funs.len() {
+            if tarjan.index[v].is_none() {
+                tarjan.visit(v);
+            }
+        }
+        let mut sccs: Vec<_> = tarjan.sccs.into_iter().filter(|c| c.len() > 1).collect();
+        for scc in &mut sccs {
+            scc.sort();
+        }
+        sccs.sort();
+        sccs
+    }
+
+    // the functions that no chain of calls from `root` reaches, `None` if
+    // there is no function `root`
+    pub fn unreachable(&self, root: &str) -> Option<Vec<usize>> {
+        let root = self.names.iter().position(|n| n == root)?;
+        let mut seen = vec![false; self.funs.len()];
+        let mut todo = vec![root];
+        seen[root] = true;
+        while let Some(v) = todo.pop() {
+            for &w in &self.calls[v] {
+                if !seen[w] {
+                    seen[w] = true;
+                    todo.push(w);
+                }
+            }
+        }
+        Some((0..self.funs.len()).filter(|&v| !seen[v]).collect())
+    }
+
+    // the graph in Graphviz's DOT language
+    pub fn dot(&self) -> String {
+        let quote = |v: usize| Json::from(&self.names[v][..]).to_string();
+        let mut s = String::from("digraph calls {\n");
+        for (v, callees) in self.calls.iter().enumerate() {
+            writeln!(s, "    {};", quote(v)).unwrap();
+            for &w in callees {
+                writeln!(s, "    {} -> {};", quote(v), quote(w)).unwrap();
+            }
+        }
+        s.push_str("}\n");
+        s
+    }
+
+    // the graph with its analysis, as a JSON object
+    pub fn json(&self, root: &str) -> String {
+        let names =
+            |vs: &[usize]| Json::Arr(vs.iter().map(|&v| self.names[v][..].into()).collect());
+        let funs = self
+            .calls
+            .iter()
+            .enumerate()
+            .map(|(v, callees)| {
+                Json::obj([
+                    ("name", self.names[v][..].into()),
+                    ("calls", names(callees)),
+                ])
+            })
+            .collect();
+        let sccs = self.mutual_recursion().iter().map(|c| names(c)).collect();
+        let unreachable = self.unreachable(root).map_or(Json::Null, |vs| names(&vs));
+        let graph = Json::obj([
+            ("functions", Json::Arr(funs)),
+            ("mutual_recursion", Json::Arr(sccs)),
+            ("unreachable", unreachable),
+        ]);
+        format!("{graph}\n")
+    }
+}
+
+fn bundled(alloc: &Alloc, loc: Loc) -> bool {
+    alloc
+        .get_file(loc.file)
+        .and_then(|p| p.to_str())
+        .is_some_and(|p| p.starts_with("std:"))
+}
+
+struct Tarjan<'g> {
+    graph: &'g CallGraph,
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next: usize,
+    sccs: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, v: usize) {
+        crate::with_stacker(|| {
+            self.index[v] = Some(self.next);
+            self.low[v] = self.next;
+            self.next += 1;
+            self.stack.push(v);
+            self.on_stack[v] = true;
+            for &w in &self.graph.calls[v] {
+                match self.index[w] {
+                    None => {
+                        self.visit(w);
+                        self.low[v] = self.low[v].min(self.low[w]);
+                    }
+                    Some(i) if self.on_stack[w] => self.low[v] = self.low[v].min(i),
+                    Some(_) => {}
+                }
+            }
+            if Some(self.low[v]) == self.index[v] {
+                let mut scc = Vec::new();
+                loop {
+                    let w = self.stack.pop().unwrap();
+                    self.on_stack[w] = false;
+                    scc.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                self.sccs.push(scc);
+            }
+        })
+    }
+}
@@ -0,0 +1,225 @@
+use std::{cmp::Ordering, fmt};
+
+// an arbitrary precision integer: sign and magnitude, the magnitude in base
+// 2^32 with the least significant digit first and no trailing zero digits
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Int {
+    neg: bool,
+    mag: Vec<u32>,
+}
+
+impl Int {
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.neg
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.mag[..] {
+            _ if self.neg => None,
+            [] => Some(0),
+            [a] => Some(a as u64),
+            [a, b] => Some((b as u64) << 32 | a as u64),
+            _ => None,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Int> {
+        let (neg, digits) = match s.strip_prefix('-') {
+            Some(d) => (true, d),
+            None => (false, s),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+        let mut mag = Vec::new();
+        for c in digits.chars() {
+            mul_add_small(&mut mag, 10, c.to_digit(10)?);
+        }
+        Some(Int::new(neg, mag))
+    }
+
+    pub fn div_rem(&self, other: &Int) -> Option<(Int, Int)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (q, r) = div_rem_mag(&self.mag, &other.mag);
+        Some((Int::new(self.neg != other.neg, q), Int::new(self.neg, r)))
+    }
+
+    fn new(neg: bool, mut mag: Vec<u32>) -> Int {
+        while mag.last() == Some(&0) {
+            mag.pop();
+        }
+        Int {
+            neg: neg && !mag.is_empty(),
+            mag,
+        }
+    }
+
+    fn neg(&self) -> Int {
+        Int::new(!self.neg, self.mag.clone())
+    }
+}
+
+impl From<u64> for Int {
+    fn from(n: u64) -> Int {
+        Int::new(false, vec![n as u32, (n >> 32) as u32])
+    }
+}
+
+impl Ord for Int {
+    fn cmp(&self, other: &Int) -> Ordering {
+        match (self.neg, other.neg) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+
+impl PartialOrd for Int {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::ops::Add for &Int {
+    type Output = Int;
+    fn add(self, other: &Int) -> Int {
+        if self.neg == other.neg {
+            return Int::new(self.neg, add_mag(&self.mag, &other.mag));
+        }
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => Int::new(other.neg, sub_mag(&other.mag, &self.mag)),
+            _ => Int::new(self.neg, sub_mag(&self.mag, &other.mag)),
+        }
+    }
+}
+
+impl std::ops::Sub for &Int {
+    type Output = Int;
+    fn sub(self, other: &Int) -> Int {
+        std::ops::Add::add(self, &other.neg())
+    }
+}
+
+impl std::ops::Mul for &Int {
+    type Output = Int;
+    fn mul(self, other: &Int) -> Int {
+        let mut res = vec![0; self.mag.len() + other.mag.len()];
+        for (i, &a) in self.mag.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.mag.iter().enumerate() {
+                let t = a as u64 * b as u64 + res[i + j] as u64 + carry;
+                res[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            res[i + other.mag.len()] = carry as u32;
+        }
+        Int::new(self.neg != other.neg, res)
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut chunks = Vec::new();
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            chunks.push(div_rem_small(&mut mag, 1_000_000_000));
+        }
+        if self.neg {
+            f.write_str("-")?;
+        }
+        match chunks.split_last() {
+            None => f.write_str("0"),
+            Some((first, rest)) => {
+                write!(f, "{first}")?;
+                rest.iter().rev().try_for_each(|c| write!(f, "{c:09}"))
+            }
+        }
+    }
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let t = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        res.push(t as u32);
+        carry = t >> 32;
+    }
+    res.push(carry as u32);
+    res
+}
+
+// `a - b` for `a >= b`
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &x) in a.iter().enumerate() {
+        let y = *b.get(i).unwrap_or(&0) as i64 + borrow;
+        let t = x as i64 - y;
+        borrow = (t < 0) as i64;
+        res.push(t.rem_euclid(1 << 32) as u32);
+    }
+    res
+}
+
+fn mul_add_small(mag: &mut Vec<u32>, m: u32, a: u32) {
+    let mut carry = a as u64;
+    for d in mag.iter_mut() {
+        let t = *d as u64 * m as u64 + carry;
+        *d = t as u32;
+        carry = t >> 32;
+    }
+    if carry > 0 {
+        mag.push(carry as u32);
+    }
+}
+
+// divides in place, returning the remainder
+fn div_rem_small(mag: &mut Vec<u32>, d: u32) -> u32 {
+    let mut rem = 0;
+    for x in mag.iter_mut().rev() {
+        let t = rem << 32 | *x as u64;
+        *x = (t / d as u64) as u32;
+        rem = t % d as u64;
+    }
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+    rem as u32
+}
+
+// schoolbook binary long division
+fn div_rem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [d] = b {
+        let mut q = a.to_vec();
+        let r = div_rem_small(&mut q, *d);
+        return (q, vec![r]);
+    }
+    let mut q = vec![0; a.len()];
+    let mut r: Vec<u32> = Vec::new();
+    for i in (0..a.len() * 32).rev() {
+        mul_add_small(&mut r, 2, a[i / 32] >> (i % 32) & 1);
+        if cmp_mag(&r, b) != Ordering::Less {
+            r = sub_mag(&r, b);
+            while r.last() == Some(&0) {
+                r.pop();
+            }
+            q[i / 32] |= 1 << (i % 32);
+        }
+    }
+    (q, r)
+}
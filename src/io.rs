@@ -12,7 +12,7 @@ use crate::{
 type CExpr = (Expr, Id);
 
 pub(crate) fn input(alloc: &mut Alloc, e: &mut Expr) {
-    let curr = std::io::stdin().bytes().next().transpose().unwrap();
+    let curr = std::io::stdin().lock().bytes().next().transpose().unwrap();
     // (byte high low)
     let src = curr.map_or(fun(EOF, e.loc()), |b| {
         let high = alloc.alloc_or_get(&format!("{:X}", b >> 4));
@@ -0,0 +1,241 @@
+use std::fmt;
+
+// just enough JSON for the language server's messages
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+const NULL: Json = Json::Null;
+
+impl Json {
+    pub(crate) fn obj<const N: usize>(fields: [(&str, Json); N]) -> Json {
+        Json::Obj(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    // the field `key`, `null` if there is none
+    pub(crate) fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Obj(fields) => fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(&NULL, |(_, v)| v),
+            _ => &NULL,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Num(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as u64),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Json> {
+        let mut p = Parser { s, i: 0 };
+        let res = p.value()?;
+        p.ws();
+        (p.i == s.len()).then_some(res)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::Str(s.into())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::Str(s)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(n: u64) -> Json {
+        Json::Num(n as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Num(n) => write!(f, "{n}"),
+            Json::Str(s) => write_str(f, s),
+            Json::Arr(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Json::Obj(fields) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_str(f, k)?;
+                    write!(f, ":{v}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn ws(&mut self) {
+        let rest = &self.s[self.i..];
+        self.i += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, lit: &str) -> bool {
+        self.ws();
+        let found = self.s[self.i..].starts_with(lit);
+        if found {
+            self.i += lit.len();
+        }
+        found
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.ws();
+        let rest = &self.s[self.i..];
+        if self.eat("null") {
+            Some(Json::Null)
+        } else if self.eat("true") {
+            Some(Json::Bool(true))
+        } else if self.eat("false") {
+            Some(Json::Bool(false))
+        } else if rest.starts_with('"') {
+            self.string().map(Json::Str)
+        } else if self.eat("[") {
+            let mut items = Vec::new();
+            if !self.eat("]") {
+                loop {
+                    items.push(self.value()?);
+                    if self.eat("]") {
+                        break;
+                    }
+                    self.eat(",").then_some(())?;
+                }
+            }
+            Some(Json::Arr(items))
+        } else if self.eat("{") {
+            let mut fields = Vec::new();
+            if !self.eat("}") {
+                loop {
+                    self.ws();
+                    let k = self.string()?;
+                    self.eat(":").then_some(())?;
+                    fields.push((k, self.value()?));
+                    if self.eat("}") {
+                        break;
+                    }
+                    self.eat(",").then_some(())?;
+                }
+            }
+            Some(Json::Obj(fields))
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                .unwrap_or(rest.len());
+            let n = rest[..len].parse().ok()?;
+            self.i += len;
+            Some(Json::Num(n))
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let mut chars = self.s[self.i..].char_indices();
+        chars.next().filter(|&(_, c)| c == '"')?;
+        let mut res = String::new();
+        loop {
+            let (i, c) = chars.next()?;
+            match c {
+                '"' => {
+                    self.i += i + 1;
+                    return Some(res);
+                }
+                '\\' => res.push(match chars.next()?.1 {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let high = code_unit(&mut chars)?;
+                        // a character outside the BMP is escaped as a UTF-16
+                        // surrogate pair
+                        let mut ahead = chars.clone();
+                        let low = match (ahead.next(), ahead.next()) {
+                            (Some((_, '\\')), Some((_, 'u'))) => code_unit(&mut ahead),
+                            _ => None,
+                        };
+                        match low {
+                            Some(low @ 0xdc00..=0xdfff) if (0xd800..0xdc00).contains(&high) => {
+                                chars = ahead;
+                                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                                    .unwrap()
+                            }
+                            _ => char::from_u32(high).unwrap_or('\u{fffd}'),
+                        }
+                    }
+                    c => c,
+                }),
+                c => res.push(c),
+            }
+        }
+    }
+}
+
+// the four hex digits after `\u`
+fn code_unit(chars: &mut impl Iterator<Item = (usize, char)>) -> Option<u32> {
+    let hex: String = chars.take(4).map(|(_, c)| c).collect();
+    u32::from_str_radix(&hex, 16).ok()
+}
@@ -1,41 +1,91 @@
-use error::{Error, ErrorTy};
-use parser::{App, Def, Expr};
+use error::{Error, ErrorTy, Loc};
+use parser::{App, Def};
 use std::{
     collections::{HashMap, HashSet},
+    path::Path,
     rc::Rc,
 };
 
 mod alloc;
+pub mod cst;
+mod dispatch;
+pub mod doc;
 mod error;
+pub mod format;
+pub mod graph;
+mod int;
 mod io;
+mod json;
+mod lift;
+mod literal;
+mod loader;
+pub mod lsp;
+mod macros;
+pub mod manifest;
+mod namespace;
+mod narrow;
+mod native;
 mod parser;
+mod pattern;
 mod token;
-mod unify;
+pub mod unify;
 
-use alloc::Id;
-pub use alloc::{Alloc, DisplayWithAlloc};
+pub use alloc::{Alloc, DisplayWithAlloc, Id};
+pub use error::Warning;
+pub use int::Int;
+pub use manifest::Manifest;
+pub use narrow::{solve, Solution};
+pub use parser::{Attr, AttrArg, Expr, Style};
 
 pub type Rules = HashMap<Id, Vec<Def>>;
 
 pub fn parse(alloc: &mut Alloc, src: String) -> Result<Rules, Error> {
-    let scanner = token::Scanner::new(&src);
-    let mut parser = parser::Parser::new(scanner);
-    let mut defs: HashMap<_, Vec<Def>> = HashMap::new();
-    while let Some(def) = parser.parse_def(alloc)? {
-        check_closed(alloc, &def)?;
-        defs.entry(def.id.clone()).or_default().push(def);
+    let mut loader = loader::Loader::new();
+    loader.load_src(alloc, &src, 0, Path::new("."))?;
+    loader.finish(alloc)
+}
+
+pub fn parse_file(alloc: &mut Alloc, path: &Path) -> Result<Rules, Error> {
+    let mut loader = loader::Loader::new();
+    loader.load_file(alloc, path, Loc::new())?;
+    loader.finish(alloc)
+}
+
+pub fn parse_project(alloc: &mut Alloc, manifest: &Manifest) -> Result<Rules, Error> {
+    let mut loader = loader::Loader::with_search(manifest.search_path());
+    if manifest.prelude {
+        loader.load_std(alloc, "prelude", Loc::new())?;
     }
-    Ok(defs)
+    loader.load_file(alloc, &manifest.entry, Loc::new())?;
+    loader.finish(alloc)
+}
+
+// like `parse_file`, with the standard modules imported beforehand
+pub fn parse_file_with_prelude(alloc: &mut Alloc, path: &Path) -> Result<Rules, Error> {
+    let mut loader = loader::Loader::new();
+    loader.load_std(alloc, "prelude", Loc::new())?;
+    loader.load_file(alloc, path, Loc::new())?;
+    loader.finish(alloc)
 }
 
 pub fn parse_expr(alloc: &mut Alloc, src: String) -> Result<Expr, Error> {
     let scanner = token::Scanner::new(&src);
-    let mut parser = parser::Parser::new(scanner);
+    let mut parser = parser::Parser::new(scanner, alloc);
     let (.., e) = parser.parse_expr(alloc, false)?;
-    parser.sc.expect_token(alloc, token::TokenTy::Eof)?;
+    parser.expect_end(alloc)?;
     Ok(e)
 }
 
+pub fn parse_goal(alloc: &mut Alloc, src: String) -> Result<(Expr, Expr), Error> {
+    let scanner = token::Scanner::new(&src);
+    let mut parser = parser::Parser::new(scanner, alloc);
+    let (.., lhs) = parser.parse_expr(alloc, false)?;
+    parser.sc.expect_token(alloc, token::TokenTy::Equal)?;
+    let (.., rhs) = parser.parse_expr(alloc, false)?;
+    parser.expect_end(alloc)?;
+    Ok((lhs, rhs))
+}
+
 pub fn apply(defs: &Rules, e: &mut Expr, alloc: &mut Alloc) -> bool {
     with_stacker(|| {
         let mut changed = false;
@@ -48,8 +98,9 @@ pub fn apply(defs: &Rules, e: &mut Expr, alloc: &mut Alloc) -> bool {
                     f.id = get_id(&f.f);
                     if is_io(&f.f) {
                         io::output(alloc, e);
-                    } else if !defs.contains_key(&f.id)
-                        || !defs[&f.id].iter().any(|def| def.apply(e))
+                    } else if (!defs.contains_key(&f.id)
+                        || !defs[&f.id].iter().any(|def| def.apply(defs, e, alloc)))
+                        && !native::reduce(alloc, e)
                     {
                         mark_reduced(e);
                         break changed;
@@ -60,8 +111,8 @@ pub fn apply(defs: &Rules, e: &mut Expr, alloc: &mut Alloc) -> bool {
                     let f_id = &*id;
                     if &alloc::INPUT == id {
                         io::input(alloc, e);
-                    } else if !defs.contains_key(&*f_id)
-                        || !defs[&*id].iter().any(|def| def.apply(e))
+                    } else if !defs.contains_key(f_id)
+                        || !defs[&*id].iter().any(|def| def.apply(defs, e, alloc))
                     {
                         break changed;
                     }
@@ -79,6 +130,7 @@ fn get_id(e: &Expr) -> Id {
         Expr::App(f) => f.id.clone(),
         Expr::RedApp(f) => f.id.clone(),
         Expr::Fun { id, .. } => id.clone(),
+        Expr::Int { .. } => alloc::INT,
         Expr::Var { .. } => unreachable!(),
     }
 }
@@ -100,18 +152,26 @@ fn mark_reduced(e: &mut Expr) {
     }
 }
 
-fn check_closed(alloc: &Alloc, def: &Def) -> Result<(), Error> {
-    let mut pat_vars = HashSet::new();
-    vars(&mut pat_vars, &def.pat);
-    let mut rep_vars = HashSet::new();
-    vars(&mut rep_vars, &def.rep);
-    let undefined: Vec<_> = rep_vars
-        .difference(&pat_vars)
+pub(crate) fn check_closed(alloc: &Alloc, def: &Def) -> Result<(), Error> {
+    let mut bound = HashSet::new();
+    vars(&mut bound, &def.pat);
+    for (guard, nf) in &def.guards {
+        check_bound(alloc, &bound, guard)?;
+        vars(&mut bound, nf);
+    }
+    check_bound(alloc, &bound, &def.rep)
+}
+
+fn check_bound(alloc: &Alloc, bound: &HashSet<Id>, e: &Expr) -> Result<(), Error> {
+    let mut used = HashSet::new();
+    vars(&mut used, e);
+    let undefined: Vec<_> = used
+        .difference(bound)
         .map(|i| alloc.get_string(i))
         .collect();
     if !undefined.is_empty() {
         Err(Error {
-            loc: def.rep.loc(),
+            loc: e.loc(),
             ty: ErrorTy::CExprError,
             desc: format!("undefined variables: {}", list(&undefined)),
         })
@@ -120,7 +180,7 @@ fn check_closed(alloc: &Alloc, def: &Def) -> Result<(), Error> {
     }
 }
 
-fn vars(v: &mut HashSet<Id>, e: &Expr) {
+pub(crate) fn vars(v: &mut HashSet<Id>, e: &Expr) {
     match e {
         Expr::Var { id, .. } => {
             v.insert(id.clone());
@@ -136,7 +196,7 @@ fn vars(v: &mut HashSet<Id>, e: &Expr) {
             vars(v, &a.arg);
         }
 
-        Expr::Fun { .. } => {}
+        Expr::Fun { .. } | Expr::Int { .. } => {}
     }
 }
 
@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    alloc::{Alloc, Id},
+    error::{Error, Loc},
+    parser::{Def, Expr},
+    pattern,
+};
+
+// while parsing, `let p = e in body` is kept as an application of this
+// symbol to `p`, `e` and `body`
+pub(crate) const LET: &str = "let;";
+
+// a lifted function and the variables it captured from its surroundings
+type Lifted = HashMap<Id, (Id, Vec<Id>)>;
+
+pub(crate) fn let_marker(alloc: &mut Alloc, p: Expr, e: Expr, body: Expr, loc: Loc) -> Expr {
+    let id = alloc.alloc_or_get(LET);
+    let f = Expr::app(Expr::app(Expr::Fun { id, loc }, p, loc), e, loc);
+    Expr::app(f, body, loc)
+}
+
+fn split_let<'e>(alloc: &mut Alloc, e: &'e Expr) -> Option<(&'e Expr, &'e Expr, &'e Expr)> {
+    let id = alloc.alloc_or_get(LET);
+    let outer = e.as_app()?;
+    let middle = outer.f.as_app()?;
+    let inner = middle.f.as_app()?;
+    match &inner.f {
+        Expr::Fun { id: id2, .. } if *id2 == id => Some((&inner.arg, &middle.arg, &outer.arg)),
+        _ => None,
+    }
+}
+
+// appends the variables of `e` not yet in `scope`, in source order, so that
+// captured variables are passed in a predictable order
+pub(crate) fn extend_scope(scope: &mut Vec<Id>, e: &Expr) {
+    match e {
+        Expr::Var { id, .. } if !scope.contains(id) => scope.push(id.clone()),
+        Expr::Var { .. } | Expr::Fun { .. } | Expr::Int { .. } => {}
+        _ => {
+            let app = e.as_app().unwrap();
+            extend_scope(scope, &app.f);
+            extend_scope(scope, &app.arg);
+        }
+    }
+}
+
+// `(name c1 c2 ...)`: a lifted function applied to what it captured
+pub(crate) fn call(name: &Id, captured: &[Id], loc: Loc) -> Expr {
+    captured.iter().fold(
+        Expr::Fun {
+            id: name.clone(),
+            loc,
+        },
+        |f, c| {
+            let arg = Expr::Var { id: c.clone(), loc };
+            Expr::app(f, arg, loc)
+        },
+    )
+}
+
+// the variables of `scope` that `e` uses, except those in `bound`. a variable
+// applied to arguments reads as a function symbol, which matching replaces
+// like the variable, so those count as uses too
+pub(crate) fn captures(scope: &[Id], bound: &HashSet<Id>, e: &Expr) -> Vec<Id> {
+    let mut used = HashSet::new();
+    symbols(&mut used, e);
+    scope
+        .iter()
+        .filter(|v| used.contains(*v) && !bound.contains(*v))
+        .cloned()
+        .collect()
+}
+
+fn symbols(used: &mut HashSet<Id>, e: &Expr) {
+    match e {
+        Expr::Var { id, .. } | Expr::Fun { id, .. } => {
+            used.insert(id.clone());
+        }
+        Expr::Int { .. } => {}
+        _ => {
+            let app = e.as_app().unwrap();
+            symbols(used, &app.f);
+            symbols(used, &app.arg);
+        }
+    }
+}
+
+fn rewrite(map: &Lifted, e: &Expr) -> Expr {
+    crate::with_stacker(|| match e {
+        Expr::Fun { id, loc } if map.contains_key(id) => {
+            let (name, captured) = &map[id];
+            call(name, captured, *loc)
+        }
+        Expr::Var { .. } | Expr::Fun { .. } | Expr::Int { .. } => e.clone(),
+        _ => {
+            let app = e.as_app().unwrap();
+            Expr::app(rewrite(map, &app.f), rewrite(map, &app.arg), app.loc)
+        }
+    })
+}
+
+// turns the rules of a `where` block into global ones: the functions it
+// declares get fresh names, and every rule takes the variables of `scope` it
+// needs, directly or through the local functions it calls, as leading
+// arguments. returns the rules and a rewrite for the enclosing definition
+pub(crate) fn lift_locals(
+    alloc: &mut Alloc,
+    owner: &Id,
+    scope: &[Id],
+    declared: &HashSet<Id>,
+    locals: Vec<Def>,
+) -> (Vec<Def>, impl Fn(&Expr) -> Expr) {
+    let names: HashSet<_> = locals.iter().map(|def| def.id.clone()).collect();
+    let mut captured: HashMap<Id, HashSet<Id>> = HashMap::new();
+    let mut calls: HashMap<Id, HashSet<Id>> = HashMap::new();
+    for def in &locals {
+        let mut bound = HashSet::new();
+        crate::vars(&mut bound, &def.pat);
+        let mut funs = HashSet::new();
+        let mut exprs = vec![&def.rep];
+        for (guard, nf) in &def.guards {
+            crate::vars(&mut bound, nf);
+            exprs.push(guard);
+        }
+        let entry = captured.entry(def.id.clone()).or_default();
+        for e in exprs {
+            entry.extend(captures(scope, &bound, e));
+            funs_of(&names, &mut funs, e);
+        }
+        calls.entry(def.id.clone()).or_default().extend(funs);
+    }
+    // a function calling another needs what that one captured
+    loop {
+        let mut changed = false;
+        for (f, callees) in &calls {
+            let needed: Vec<_> = callees.iter().flat_map(|g| captured[g].clone()).collect();
+            let own = captured.get_mut(f).unwrap();
+            for v in needed {
+                changed |= own.insert(v);
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut map = Lifted::new();
+    for name in names {
+        let new = if declared.contains(&name) {
+            let local = match alloc.get_string(&name) {
+                "_" => "lambda",
+                name => name,
+            };
+            let base = format!("{};{local}", alloc.get_string(owner));
+            alloc.fresh(&base)
+        } else {
+            name.clone()
+        };
+        let vars = scope
+            .iter()
+            .filter(|v| captured[&name].contains(*v))
+            .cloned()
+            .collect();
+        map.insert(name, (new, vars));
+    }
+    let defs = locals
+        .into_iter()
+        .map(|def| Def {
+            id: map[&def.id].0.clone(),
+            loc: def.loc,
+            pat: rewrite(&map, &def.pat),
+            rep: rewrite(&map, &def.rep),
+            guards: def
+                .guards
+                .iter()
+                .map(|(g, nf)| (rewrite(&map, g), rewrite(&map, nf)))
+                .collect(),
+            attrs: Vec::new(),
+        })
+        .collect();
+    (defs, move |e: &Expr| rewrite(&map, e))
+}
+
+fn funs_of(names: &HashSet<Id>, funs: &mut HashSet<Id>, e: &Expr) {
+    match e {
+        Expr::Fun { id, .. } if names.contains(id) => {
+            funs.insert(id.clone());
+        }
+        Expr::Var { .. } | Expr::Fun { .. } | Expr::Int { .. } => {}
+        _ => {
+            let app = e.as_app().unwrap();
+            funs_of(names, funs, &app.f);
+            funs_of(names, funs, &app.arg);
+        }
+    }
+}
+
+// replaces every `let p = e in body` in `e`, innermost first, by a call to a
+// fresh rule `(name captured... p) = body` applied to `e`
+pub(crate) fn lift_lets(
+    alloc: &mut Alloc,
+    owner: &Id,
+    scope: &[Id],
+    e: &Expr,
+    out: &mut Vec<Def>,
+) -> Result<Expr, Error> {
+    crate::with_stacker(|| {
+        if let Some((p, bound, body)) = split_let(alloc, e) {
+            let (p, bound, body) = (p.clone(), bound.clone(), body.clone());
+            let loc = e.loc();
+            let bound = lift_lets(alloc, owner, scope, &bound, out)?;
+            let mut inner = scope.to_vec();
+            extend_scope(&mut inner, &p);
+            let body = lift_lets(alloc, owner, &inner, &body, out)?;
+
+            let mut p_vars = HashSet::new();
+            crate::vars(&mut p_vars, &p);
+            let captured = captures(scope, &p_vars, &body);
+            let base = format!("{};let", alloc.get_string(owner));
+            let name = alloc.fresh(&base);
+            let mut guards = Vec::new();
+            let pat = pattern::desugar(alloc, &p, &mut guards, &mut 0)?;
+            out.push(Def {
+                id: name.clone(),
+                loc,
+                pat: Expr::app(call(&name, &captured, loc), pat, loc),
+                rep: body,
+                guards,
+                attrs: Vec::new(),
+            });
+            return Ok(Expr::app(call(&name, &captured, loc), bound, loc));
+        }
+        Ok(match e.as_app() {
+            Some(app) => {
+                let f = lift_lets(alloc, owner, scope, &app.f, out)?;
+                let arg = lift_lets(alloc, owner, scope, &app.arg, out)?;
+                Expr::app(f, arg, app.loc)
+            }
+            None => e.clone(),
+        })
+    })
+}
@@ -0,0 +1,179 @@
+use std::{borrow::Cow, rc::Rc};
+
+use crate::{
+    alloc::{Alloc, Id, BYTE},
+    error::Loc,
+    parser::Expr,
+    token::is_break,
+};
+
+// `(byte (H) (L))`, the same shape `io::input` produces
+pub(crate) fn byte(alloc: &mut Alloc, b: u8, loc: Loc) -> Expr {
+    let high = alloc.alloc_or_get(&format!("{:X}", b >> 4));
+    let low = alloc.alloc_or_get(&format!("{:X}", b & 15));
+    let f = Expr::app(
+        Expr::Fun { id: BYTE, loc },
+        Expr::Fun { id: high, loc },
+        loc,
+    );
+    Expr::app(f, Expr::Fun { id: low, loc }, loc)
+}
+
+pub(crate) fn decode_byte(alloc: &Alloc, e: &Expr) -> Option<u8> {
+    let outer = e.as_app()?;
+    let inner = outer.f.as_app()?;
+    match (&inner.f, &inner.arg, &outer.arg) {
+        (Expr::Fun { id, .. }, Expr::Fun { id: high, .. }, Expr::Fun { id: low, .. })
+            if *id == BYTE =>
+        {
+            let digit = |id| match alloc.get_string(id) {
+                d if d.len() == 1 => u8::from_str_radix(d, 16).ok(),
+                _ => None,
+            };
+            Some(digit(high)? << 4 | digit(low)?)
+        }
+        _ => None,
+    }
+}
+
+// the text of a non-empty list of bytes forming valid UTF-8
+pub(crate) fn decode_string(alloc: &Alloc, mut e: &Expr) -> Option<String> {
+    let mut bytes = Vec::new();
+    loop {
+        match e {
+            Expr::Fun { id, .. } if alloc.get_string(id) == "nil" && !bytes.is_empty() => {
+                return String::from_utf8(bytes).ok()
+            }
+            _ => {
+                let outer = e.as_app()?;
+                let inner = outer.f.as_app()?;
+                match &inner.f {
+                    Expr::Fun { id, .. } if alloc.get_string(id) == "cons" => {}
+                    _ => return None,
+                }
+                bytes.push(decode_byte(alloc, &inner.arg)?);
+                e = &outer.arg;
+            }
+        }
+    }
+}
+
+// how `#n` is spelled, set by `pragma numerals unary s z;`,
+// `pragma numerals binary e o i;` or `pragma numerals native;`
+#[derive(Clone, Debug)]
+pub(crate) enum Numerals {
+    // `(s (s (z)))`
+    Unary { succ: Id, zero: Id },
+    // least significant bit first: `(o (i (e)))` is 2, with no `(o (e))`
+    Binary { zero: Id, even: Id, odd: Id },
+    // `Expr::Int`
+    Native,
+}
+
+impl Numerals {
+    pub(crate) fn unary(alloc: &mut Alloc) -> Self {
+        Numerals::Unary {
+            succ: alloc.alloc_or_get("s"),
+            zero: alloc.alloc_or_get("z"),
+        }
+    }
+
+    pub(crate) fn binary(alloc: &mut Alloc) -> Self {
+        Numerals::Binary {
+            zero: alloc.alloc_or_get("e"),
+            even: alloc.alloc_or_get("o"),
+            odd: alloc.alloc_or_get("i"),
+        }
+    }
+
+    pub(crate) fn encode(&self, n: u64, loc: Loc) -> Expr {
+        let fun = |id: &Id| Expr::Fun {
+            id: id.clone(),
+            loc,
+        };
+        match self {
+            Numerals::Unary { succ, zero } => {
+                (0..n).fold(fun(zero), |e, _| Expr::app(fun(succ), e, loc))
+            }
+            Numerals::Binary { zero, .. } if n == 0 => fun(zero),
+            Numerals::Binary { even, odd, .. } => {
+                let bit = if n.is_multiple_of(2) { even } else { odd };
+                Expr::app(fun(bit), self.encode(n / 2, loc), loc)
+            }
+            Numerals::Native => Expr::Int {
+                n: Rc::new(n.into()),
+                loc,
+            },
+        }
+    }
+}
+
+// the value of `e` as a numeral with the default names, either representation
+pub(crate) fn decode_nat(alloc: &Alloc, e: &Expr) -> Option<u64> {
+    decode_unary(alloc, e).or_else(|| decode_binary(alloc, e))
+}
+
+pub(crate) fn decode_unary(alloc: &Alloc, mut e: &Expr) -> Option<u64> {
+    let mut n: u64 = 0;
+    loop {
+        match e {
+            Expr::Fun { id, .. } if alloc.get_string(id) == "z" => return Some(n),
+            _ => {
+                let app = e.as_app()?;
+                match &app.f {
+                    Expr::Fun { id, .. } if alloc.get_string(id) == "s" => {}
+                    _ => return None,
+                }
+                n = n.checked_add(1)?;
+                e = &app.arg;
+            }
+        }
+    }
+}
+
+fn decode_binary(alloc: &Alloc, e: &Expr) -> Option<u64> {
+    if let Expr::Fun { id, .. } = e {
+        return (alloc.get_string(id) == "e").then_some(0);
+    }
+    let app = e.as_app()?;
+    let bit = match &app.f {
+        Expr::Fun { id, .. } if alloc.get_string(id) == "o" => 0,
+        Expr::Fun { id, .. } if alloc.get_string(id) == "i" => 1,
+        _ => return None,
+    };
+    match decode_binary(alloc, &app.arg)? {
+        0 if bit == 0 => None,
+        n => n.checked_mul(2)?.checked_add(bit),
+    }
+}
+
+// the symbol `name` as written in source, quoted if it would not scan as one
+pub(crate) fn symbol(name: &str) -> Cow<'_, str> {
+    if name.is_empty() || name.contains(is_break) {
+        Cow::Owned(escape(name, '|'))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+// `s` in the literal syntax the scanner accepts
+pub(crate) fn escape(s: &str, quote: char) -> String {
+    let mut res = String::from(quote);
+    for c in s.chars() {
+        match c {
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            '\r' => res.push_str("\\r"),
+            '\0' => res.push_str("\\0"),
+            '\\' => res.push_str("\\\\"),
+            c if c == quote => {
+                res.push('\\');
+                res.push(c)
+            }
+            c if c.is_ascii_control() => res.push_str(&format!("\\x{:02X}", c as u8)),
+            c => res.push(c),
+        }
+    }
+    res.push(quote);
+    res
+}
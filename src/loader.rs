@@ -0,0 +1,293 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    alloc::{Alloc, Id},
+    cst,
+    error::{Error, ErrorTy, Loc},
+    namespace,
+    parser::{AttrArg, Def, Expr, Item, Parser},
+    Rules,
+};
+
+const STD: &[(&str, &str)] = &[
+    ("bool", include_str!("../std/bool.rhk")),
+    ("peano", include_str!("../std/peano.rhk")),
+    ("lists", include_str!("../std/lists.rhk")),
+    ("io", include_str!("../std/io.rhk")),
+    ("prelude", include_str!("../std/prelude.rhk")),
+];
+
+pub(crate) struct Loader {
+    pub(crate) defs: Rules,
+    // files that asked for `pragma dispatch specific;`
+    specific: HashSet<usize>,
+    // files currently being loaded, innermost last
+    stack: Vec<PathBuf>,
+    loaded: HashSet<PathBuf>,
+    // private functions by qualified name, and the module owning each file
+    private: HashMap<Id, Id>,
+    modules: HashMap<usize, Id>,
+    // directories tried, in order, for imports not found next to the importer
+    search: Vec<PathBuf>,
+}
+
+impl Loader {
+    pub(crate) fn new() -> Self {
+        Self {
+            defs: Rules::new(),
+            specific: HashSet::new(),
+            stack: Vec::new(),
+            loaded: HashSet::new(),
+            private: HashMap::new(),
+            modules: HashMap::new(),
+            search: Vec::new(),
+        }
+    }
+
+    pub(crate) fn with_search(search: Vec<PathBuf>) -> Self {
+        Self {
+            search,
+            ..Self::new()
+        }
+    }
+
+    pub(crate) fn load_file(
+        &mut self,
+        alloc: &mut Alloc,
+        path: &Path,
+        loc: Loc,
+    ) -> Result<(), Error> {
+        let path = fs::canonicalize(path)
+            .map_err(|e| import_error(loc, format!("could not find `{}`: {e}", path.display())))?;
+        if !self.enter(&path, loc)? {
+            return Ok(());
+        }
+        let src = fs::read_to_string(&path)
+            .map_err(|e| import_error(loc, format!("could not read `{}`: {e}", path.display())))?;
+        let src = match path.to_str() {
+            Some(p) if p.ends_with(".rhk.md") => literate_code(&src),
+            _ => src,
+        };
+
+        let file = alloc.add_file(&path);
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        self.load_src(alloc, &src, file, &dir)?;
+        self.stack.pop();
+        Ok(())
+    }
+
+    // `import "std:name";` refers to a module bundled with the interpreter
+    pub(crate) fn load_std(
+        &mut self,
+        alloc: &mut Alloc,
+        name: &str,
+        loc: Loc,
+    ) -> Result<(), Error> {
+        let src = STD
+            .iter()
+            .find(|(n, _)| *n == name)
+            .ok_or_else(|| import_error(loc, format!("no standard module named `{name}`")))?
+            .1;
+        let path = PathBuf::from(format!("std:{name}"));
+        if !self.enter(&path, loc)? {
+            return Ok(());
+        }
+
+        let file = alloc.add_file(&path);
+        self.load_src(alloc, src, file, Path::new("."))?;
+        self.stack.pop();
+        Ok(())
+    }
+
+    // pushes `path` onto the stack, unless it was already loaded
+    fn enter(&mut self, path: &Path, loc: Loc) -> Result<bool, Error> {
+        if let Some(i) = self.stack.iter().position(|p| p == path) {
+            let cycle: Vec<_> = self.stack[i..]
+                .iter()
+                .map(|p| p.as_path())
+                .chain([path])
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(import_error(
+                loc,
+                format!("import cycle: {}", cycle.join(" -> ")),
+            ));
+        }
+        // a file imported along several paths is only loaded once
+        if !self.loaded.insert(path.to_path_buf()) {
+            return Ok(false);
+        }
+        self.stack.push(path.to_path_buf());
+        Ok(true)
+    }
+
+    // imported definitions are added where the `import` appears, so each
+    // function keeps the clause order of a textual include
+    pub(crate) fn load_src(
+        &mut self,
+        alloc: &mut Alloc,
+        src: &str,
+        file: usize,
+        dir: &Path,
+    ) -> Result<(), Error> {
+        alloc.add_source(file, src);
+        let cst = cst::parse(alloc, src, file)?;
+        let mut parser = Parser::new(cst.scanner(), alloc);
+        // a module's definitions are held back until its names are known
+        let mut local = Vec::new();
+        while let Some(item) = parser.parse_item(alloc)? {
+            match item {
+                Item::Def(def) => {
+                    crate::check_closed(alloc, &def)?;
+                    if parser.module.is_some() {
+                        local.push(def);
+                    } else {
+                        self.add(def);
+                    }
+                }
+                Item::Import(loc, path) => match path.strip_prefix("std:") {
+                    Some(name) => self.load_std(alloc, name, loc)?,
+                    None => self.load_file(alloc, &self.resolve(dir, &path), loc)?,
+                },
+            }
+        }
+        if parser.specific {
+            self.specific.insert(file);
+        }
+
+        if let Some(module) = parser.module {
+            let (local, names) = namespace::qualify(alloc, &module, local);
+            for def in local {
+                self.add(def);
+            }
+            for name in parser.private {
+                self.private.insert(names[&name].clone(), module.clone());
+            }
+            self.modules.insert(file, module);
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, dir: &Path, path: &str) -> PathBuf {
+        let local = dir.join(path);
+        if local.exists() {
+            return local;
+        }
+        self.search
+            .iter()
+            .map(|d| d.join(path))
+            .find(|p| p.exists())
+            .unwrap_or(local)
+    }
+
+    fn add(&mut self, def: Def) {
+        self.defs.entry(def.id.clone()).or_default().push(def);
+    }
+
+    pub(crate) fn finish(mut self, alloc: &mut Alloc) -> Result<Rules, Error> {
+        namespace::check_private(alloc, &self.defs, &self.private, &self.modules)?;
+        warn_deprecated(alloc, &self.defs);
+        crate::dispatch::order_by_specificity(alloc, &mut self.defs, &self.specific)?;
+        Ok(self.defs)
+    }
+}
+
+// the fenced code blocks tagged `rhokell` of a Markdown file, with every other
+// line left empty, so that locations in the code are those in the file
+pub(crate) fn literate_code(src: &str) -> String {
+    let mut res = String::with_capacity(src.len());
+    // the fence of the block we are in, and whether it is code
+    let mut fence: Option<(&str, bool)> = None;
+    for line in src.split_inclusive('\n') {
+        let text = line.trim();
+        let marker_len = |c| text.len() - text.trim_start_matches(c).len();
+        let code = match fence {
+            Some((open, code)) => {
+                let close = &text[..marker_len(open.as_bytes()[0] as char)];
+                if close.len() >= open.len() && close.len() == text.len() {
+                    fence = None;
+                    false
+                } else {
+                    code
+                }
+            }
+            None => {
+                let n = marker_len('`').max(marker_len('~'));
+                if n >= 3 {
+                    let info = text[n..].split_whitespace().next();
+                    fence = Some((&text[..n], info == Some("rhokell")));
+                }
+                false
+            }
+        };
+        if code {
+            res.push_str(line);
+        } else if line.ends_with('\n') {
+            res.push('\n');
+        }
+    }
+    res
+}
+
+// warns about each use of a `@deprecated` function outside its own rules and
+// those lifted out of them
+fn warn_deprecated(alloc: &mut Alloc, defs: &Rules) {
+    let deprecated: HashMap<_, _> = defs
+        .iter()
+        .filter_map(|(id, rules)| {
+            let attr = rules.first()?.attr(alloc, "deprecated")?;
+            let note = match attr.args.first() {
+                Some(AttrArg::Str(note)) => format!(": {note}"),
+                _ => String::new(),
+            };
+            Some((id.clone(), note))
+        })
+        .collect();
+    if deprecated.is_empty() {
+        return;
+    }
+    let mut uses = Vec::new();
+    for def in defs.values().flatten() {
+        let mut funs = Vec::new();
+        fun_uses(&mut funs, &def.rep);
+        for (guard, _) in &def.guards {
+            fun_uses(&mut funs, guard);
+        }
+        let owner = alloc.get_string(&def.id);
+        for (id, loc) in funs {
+            let name = alloc.get_string(&id);
+            let own = owner == name || owner.strip_prefix(name).is_some_and(|s| s.starts_with(';'));
+            if !own && deprecated.contains_key(&id) {
+                uses.push((loc, format!("`{name}` is deprecated{}", deprecated[&id])));
+            }
+        }
+    }
+    uses.sort_by_key(|(loc, _)| (loc.file, loc.line, loc.col));
+    for (loc, desc) in uses {
+        alloc.warn(loc, desc);
+    }
+}
+
+pub(crate) fn fun_uses(out: &mut Vec<(Id, Loc)>, e: &Expr) {
+    crate::with_stacker(|| match e {
+        Expr::Fun { id, loc } => out.push((id.clone(), *loc)),
+        Expr::Var { .. } | Expr::Int { .. } => {}
+        _ => {
+            let app = e.as_app().unwrap();
+            fun_uses(out, &app.f);
+            fun_uses(out, &app.arg);
+        }
+    })
+}
+
+fn import_error(loc: Loc, desc: String) -> Error {
+    Error {
+        loc,
+        ty: ErrorTy::ImportError,
+        desc,
+    }
+}
@@ -0,0 +1,600 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    alloc::{Alloc, DisplayWithAlloc, Id},
+    cst::{self, Cst, SyntaxToken, TokenTy},
+    error::{Error, Loc, Warning},
+    json::Json,
+    literal,
+    loader::{self, Loader},
+    parser::{AttrArg, Expr},
+    token, Rules,
+};
+
+// a language server speaking JSON-RPC over `input` and `output`, until the
+// client sends `exit` or closes `input`
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server {
+        docs: HashMap::new(),
+    };
+    while let Some(msg) = read_message(&mut input)? {
+        let Some(msg) = Json::parse(&msg) else {
+            let reply = error(Json::Null, -32700, "the message is not valid JSON".into());
+            write_message(&mut output, &reply)?;
+            continue;
+        };
+        let method = msg.get("method").as_str().unwrap_or("");
+        if method == "exit" {
+            break;
+        }
+        for reply in server.handle(method, &msg) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(n) = line.strip_prefix("Content-Length:") {
+            len = n.trim().parse().ok();
+        }
+    }
+    let invalid = |desc: &str| io::Error::new(io::ErrorKind::InvalidData, desc);
+    let len = len.ok_or_else(|| invalid("missing Content-Length"))?;
+    let mut buf = vec![0; len];
+    input.read_exact(&mut buf)?;
+    String::from_utf8(buf)
+        .map(Some)
+        .map_err(|_| invalid("message is not UTF-8"))
+}
+
+fn write_message(output: &mut impl Write, msg: &Json) -> io::Result<()> {
+    let s = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{s}", s.len())?;
+    output.flush()
+}
+
+struct Server {
+    // the text of each open document, by URI
+    docs: HashMap<String, String>,
+}
+
+// what the loader makes of a document
+struct Analysis {
+    alloc: Alloc,
+    file: usize,
+    cst: Option<Cst>,
+    rules: Result<Rules, Error>,
+    warnings: Vec<Warning>,
+}
+
+impl Server {
+    // the responses and notifications for `msg`
+    fn handle(&mut self, method: &str, msg: &Json) -> Vec<Json> {
+        let id = msg.get("id").clone();
+        let params = msg.get("params");
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+        let result = match method {
+            "initialize" => Json::obj([(
+                "capabilities",
+                Json::obj([
+                    ("textDocumentSync", 1.into()),
+                    ("definitionProvider", true.into()),
+                    ("referencesProvider", true.into()),
+                    ("hoverProvider", true.into()),
+                    ("completionProvider", Json::obj([])),
+                ]),
+            )]),
+            "shutdown" => Json::Null,
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str();
+                self.docs.insert(uri.into(), text.unwrap_or("").into());
+                return vec![self.diagnostics(uri)];
+            }
+            "textDocument/didChange" => {
+                if let Json::Arr(changes) = params.get("contentChanges") {
+                    if let Some(text) = changes.last().and_then(|c| c.get("text").as_str()) {
+                        self.docs.insert(uri.into(), text.into());
+                    }
+                }
+                return vec![self.diagnostics(uri)];
+            }
+            "textDocument/didClose" => {
+                self.docs.remove(uri);
+                return vec![publish(uri, Vec::new())];
+            }
+            "textDocument/definition" => self.definition(uri, params),
+            "textDocument/references" => self.references(uri, params),
+            "textDocument/hover" => self.hover(uri, params),
+            "textDocument/completion" => self.completion(uri, params),
+            _ if id == Json::Null => return Vec::new(),
+            _ => return vec![error(id, -32601, format!("unknown method `{method}`"))],
+        };
+        if id == Json::Null {
+            return Vec::new();
+        }
+        vec![Json::obj([
+            ("jsonrpc", "2.0".into()),
+            ("id", id),
+            ("result", result),
+        ])]
+    }
+
+    fn analyze(&self, uri: &str) -> Analysis {
+        let text = self.docs.get(uri).map_or("", String::as_str);
+        let src = match uri.ends_with(".rhk.md") {
+            true => loader::literate_code(text),
+            false => text.to_string(),
+        };
+        let path = uri_path(uri);
+        let mut alloc = Alloc::new();
+        let file = alloc.add_file(&path);
+        let cst = cst::parse(&mut alloc, &src, file).ok();
+        let mut loader = Loader::new();
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let loaded = loader.load_src(&mut alloc, &src, file, dir);
+        let rules = loaded.and_then(|()| loader.finish(&mut alloc));
+        let warnings = alloc.take_warnings();
+        Analysis {
+            alloc,
+            file,
+            cst,
+            rules,
+            warnings,
+        }
+    }
+
+    fn diagnostics(&self, uri: &str) -> Json {
+        let a = self.analyze(uri);
+        let mut diags = Vec::new();
+        if let Err(e) = &a.rules {
+            diags.push(a.diagnostic(e.loc, format!("{}: {}", e.ty, e.desc), 1));
+        }
+        for w in &a.warnings {
+            diags.push(a.diagnostic(w.loc, w.desc.clone(), 2));
+        }
+        publish(uri, diags)
+    }
+
+    fn definition(&self, uri: &str, params: &Json) -> Json {
+        let a = self.analyze(uri);
+        let Some(name) = a.function_at(params) else {
+            return Json::Null;
+        };
+        let defs = a.defs(&name);
+        Json::Arr(
+            defs.iter()
+                .filter_map(|def| a.location(def.loc, 1))
+                .collect(),
+        )
+    }
+
+    // every use of the function at the position, found through the rules
+    // that this and the other open documents load, imported files included.
+    // variables and functions of other modules with the same name are not
+    // uses of it
+    fn references(&self, uri: &str, params: &Json) -> Json {
+        let a = self.analyze(uri);
+        let Some(name) = a.function_at(params) else {
+            return Json::Null;
+        };
+        let files = a.def_files(&name);
+        let declarations = *params.get("context").get("includeDeclaration") == Json::Bool(true);
+        let mut uris: Vec<_> = self.docs.keys().filter(|u| *u != uri).collect();
+        uris.sort();
+        let others = uris.into_iter().map(|uri| self.analyze(uri));
+
+        let mut res = BTreeMap::new();
+        for a in std::iter::once(a).chain(others) {
+            if a.def_files(&name) != files {
+                continue;
+            }
+            for (loc, len) in a.uses(&name, declarations) {
+                let Some(path) = a.alloc.get_file(loc.file) else {
+                    continue;
+                };
+                let key = (path.to_path_buf(), loc.line, loc.col);
+                if let Some(location) = a.location(loc, len) {
+                    res.entry(key).or_insert(location);
+                }
+            }
+        }
+        Json::Arr(res.into_values().collect())
+    }
+
+    // the documentation and the clauses of the function at the position
+    fn hover(&self, uri: &str, params: &Json) -> Json {
+        let a = self.analyze(uri);
+        let Some(name) = a.function_at(params) else {
+            return Json::Null;
+        };
+        let mut docs: Vec<String> = Vec::new();
+        let mut clauses: Vec<String> = Vec::new();
+        for def in a.defs(&name) {
+            for attr in &def.attrs {
+                let is_doc = a.alloc.get_string(&attr.name) == "doc";
+                if let (true, [AttrArg::Str(doc)]) = (is_doc, &attr.args[..]) {
+                    if !docs.contains(doc) {
+                        docs.push(doc.clone());
+                    }
+                }
+            }
+            let clause = a.source(def.loc).unwrap_or_else(|| {
+                let (pat, rep) = (def.pat.to_string(&a.alloc), def.rep.to_string(&a.alloc));
+                format!("{pat} = {rep};")
+            });
+            if !clauses.contains(&clause) {
+                clauses.push(clause);
+            }
+        }
+        if clauses.is_empty() {
+            return Json::Null;
+        }
+        let mut value = docs.join("\n\n");
+        if !value.is_empty() {
+            value.push_str("\n\n");
+        }
+        value.push_str(&format!("```rhokell\n{}\n```", clauses.join("\n")));
+        let contents = Json::obj([("kind", "markdown".into()), ("value", value.into())]);
+        Json::obj([("contents", contents)])
+    }
+
+    // the symbols known while loading the document that start like the word
+    // before the position
+    fn completion(&self, uri: &str, params: &Json) -> Json {
+        let mut a = self.analyze(uri);
+        let (line, character) = position(params);
+        let text = self.docs.get(uri).map_or("", String::as_str);
+        let line = text.lines().nth(line).unwrap_or("");
+        let character = chars(line, character);
+        let line: Vec<char> = line.chars().collect();
+        let before = &line[..character.min(line.len())];
+        let start = before
+            .iter()
+            .rposition(|&c| token::is_break(c))
+            .map_or(0, |i| i + 1);
+        let prefix: String = before[start..].iter().collect();
+
+        let mut symbols: Vec<String> = a
+            .alloc
+            .symbols()
+            .filter(|s| !s.is_empty() && !s.contains(';') && !s.starts_with(['#', '@']))
+            .filter(|s| s.starts_with(&prefix))
+            .map(String::from)
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        let items = symbols
+            .into_iter()
+            .map(|s| {
+                let id = a.alloc.alloc_or_get(&s);
+                let function = a.rules.as_ref().is_ok_and(|rules| rules.contains_key(&id));
+                let kind = if function { 3 } else { 6 };
+                Json::obj([("label", s.into()), ("kind", kind.into())])
+            })
+            .collect();
+        Json::Arr(items)
+    }
+}
+
+impl Analysis {
+    // the identifier at the position of a request
+    fn ident_at(&self, params: &Json) -> Option<&SyntaxToken> {
+        let (line, character) = position(params);
+        let character = chars(
+            self.text(self.file).lines().nth(line).unwrap_or(""),
+            character,
+        );
+        let (line, col) = (line as u64 + 1, character as u64 + 1);
+        self.cst.as_ref()?.tokens().into_iter().find(|tok| {
+            let loc = tok.loc();
+            let end = loc.col + width(&tok.text) as u64;
+            let inside = loc.line == line && loc.col <= col && col <= end;
+            inside && matches!(tok.kind(), TokenTy::Ident(_))
+        })
+    }
+
+    // the name of the function the identifier at the position refers to
+    fn function_at(&self, params: &Json) -> Option<String> {
+        let tok = self.ident_at(params)?;
+        self.symbols().into_iter().find_map(|(id, loc, _)| {
+            let name = self.alloc.get_string(id);
+            let here = loc.file == self.file && same(loc, tok.loc());
+            (here && spells(name, &tok.text)).then(|| name.to_string())
+        })
+    }
+
+    // every function symbol in the loaded rules, and whether it is the one
+    // a clause defines. outside the pattern, a symbol bound as a variable
+    // stands for the function passed in, so it is left out
+    fn symbols(&self) -> Vec<(&Id, Loc, bool)> {
+        let Ok(rules) = &self.rules else {
+            return Vec::new();
+        };
+        let mut res = Vec::new();
+        for def in rules.values().flatten() {
+            let head = head_loc(&def.pat);
+            funs(&def.pat, &mut |id, loc| {
+                res.push((id, loc, *id == def.id && same(loc, head)));
+            });
+            let mut bound = HashSet::new();
+            crate::vars(&mut bound, &def.pat);
+            for (guard, nf) in &def.guards {
+                funs(guard, &mut |id, loc| {
+                    if !bound.contains(id) {
+                        res.push((id, loc, false));
+                    }
+                });
+                crate::vars(&mut bound, nf);
+                funs(nf, &mut |id, loc| res.push((id, loc, false)));
+            }
+            funs(&def.rep, &mut |id, loc| {
+                if !bound.contains(id) {
+                    res.push((id, loc, false));
+                }
+            });
+        }
+        res
+    }
+
+    // the files with clauses of `name`
+    fn def_files(&self, name: &str) -> Vec<PathBuf> {
+        let Ok(rules) = &self.rules else {
+            return Vec::new();
+        };
+        let mut files: Vec<_> = rules
+            .iter()
+            .filter(|(id, _)| self.alloc.get_string(id) == name)
+            .flat_map(|(_, defs)| defs)
+            .filter_map(|def| self.alloc.get_file(def.loc.file))
+            .map(Path::to_path_buf)
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    // where the source writes the function `name`, with the length of each
+    // use. a symbol that a literal or a macro stands for is not written there
+    fn uses(&self, name: &str, declarations: bool) -> Vec<(Loc, usize)> {
+        let mut tokens: HashMap<usize, Vec<(u64, u64, String)>> = HashMap::new();
+        let mut res = Vec::new();
+        for (id, loc, declaration) in self.symbols() {
+            if self.alloc.get_string(id) != name || (declaration && !declarations) {
+                continue;
+            }
+            let idents = tokens
+                .entry(loc.file)
+                .or_insert_with(|| self.idents(loc.file));
+            let written = idents.iter().find(|(line, col, text)| {
+                (*line, *col) == (loc.line, loc.col) && spells(name, text)
+            });
+            if let Some((_, _, text)) = written {
+                res.push((loc, width(text)));
+            }
+        }
+        res
+    }
+
+    // the position and text of each identifier of `file`
+    fn idents(&self, file: usize) -> Vec<(u64, u64, String)> {
+        let parsed;
+        let cst = match file == self.file {
+            true => self.cst.as_ref(),
+            false => {
+                parsed = cst::parse(&mut Alloc::new(), self.text(file), file).ok();
+                parsed.as_ref()
+            }
+        };
+        let Some(cst) = cst else {
+            return Vec::new();
+        };
+        cst.tokens()
+            .into_iter()
+            .filter(|tok| matches!(tok.kind(), TokenTy::Ident(_)))
+            .map(|tok| (tok.loc().line, tok.loc().col, tok.text.clone()))
+            .collect()
+    }
+
+    // the code the locations in `file` refer to, as it was loaded
+    fn text(&self, file: usize) -> &str {
+        self.alloc.get_source(file).unwrap_or("")
+    }
+
+    // the rules for the function `name`
+    fn defs(&self, name: &str) -> Vec<&crate::Def> {
+        let Ok(rules) = &self.rules else {
+            return Vec::new();
+        };
+        rules
+            .iter()
+            .filter(|(id, _)| self.alloc.get_string(id) == name)
+            .flat_map(|(_, defs)| defs)
+            .collect()
+    }
+
+    fn location(&self, loc: Loc, len: usize) -> Option<Json> {
+        let path = self.alloc.get_file(loc.file)?;
+        if path.to_str().is_some_and(|p| p.starts_with("std:")) {
+            return None;
+        }
+        Some(Json::obj([
+            ("uri", path_uri(path).into()),
+            ("range", range(self.text(loc.file), loc, len)),
+        ]))
+    }
+
+    // the source of the item of this document with a token at `loc`
+    fn source(&self, loc: Loc) -> Option<String> {
+        if loc.file != self.file {
+            return None;
+        }
+        Some(cst::text(&self.cst.as_ref()?.item(loc)?))
+    }
+
+    fn diagnostic(&self, loc: Loc, message: String, severity: u64) -> Json {
+        let (range, message) = if loc.file == self.file {
+            let len = self.token(loc).map_or(1, |tok| width(&tok.text).max(1));
+            (range(self.text(self.file), loc, len), message)
+        } else {
+            let message = format!("{}: {message}", loc.describe(&self.alloc));
+            (range("", Loc::new(), 0), message)
+        };
+        Json::obj([
+            ("range", range),
+            ("severity", severity.into()),
+            ("source", "rhokell".into()),
+            ("message", message.into()),
+        ])
+    }
+
+    fn token(&self, loc: Loc) -> Option<&SyntaxToken> {
+        let cst = self.cst.as_ref()?;
+        cst.tokens().into_iter().find(|tok| same(tok.loc(), loc))
+    }
+}
+
+fn same(a: Loc, b: Loc) -> bool {
+    (a.line, a.col) == (b.line, b.col)
+}
+
+fn width(s: &str) -> usize {
+    s.chars().count()
+}
+
+fn position(params: &Json) -> (usize, usize) {
+    let pos = params.get("position");
+    let get = |key| pos.get(key).as_u64().unwrap_or(0) as usize;
+    (get("line"), get("character"))
+}
+
+// the `len` characters at `loc` in `text`, as a range in UTF-16 code
+// units, which is how LSP counts characters
+fn range(text: &str, loc: Loc, len: usize) -> Json {
+    let line = text.lines().nth(loc.line as usize - 1).unwrap_or("");
+    let pos = |chars: usize| {
+        Json::obj([
+            ("line", (loc.line - 1).into()),
+            ("character", (utf16(line, chars) as u64).into()),
+        ])
+    };
+    let col = loc.col as usize - 1;
+    Json::obj([("start", pos(col)), ("end", pos(col + len))])
+}
+
+// the first `chars` characters of `line` in UTF-16 code units
+fn utf16(line: &str, chars: usize) -> usize {
+    let units: usize = line.chars().take(chars).map(char::len_utf16).sum();
+    units + chars.saturating_sub(line.chars().count())
+}
+
+// how many characters of `line` the first `units` UTF-16 code units take
+fn chars(line: &str, units: usize) -> usize {
+    let (mut n, mut seen) = (0, 0);
+    for c in line.chars() {
+        if seen >= units {
+            break;
+        }
+        seen += c.len_utf16();
+        n += 1;
+    }
+    n + units.saturating_sub(seen)
+}
+
+// the location of the symbol a pattern defines
+fn head_loc(e: &Expr) -> Loc {
+    match e.as_app() {
+        Some(app) => head_loc(&app.f),
+        None => e.loc(),
+    }
+}
+
+fn funs<'e>(e: &'e Expr, f: &mut impl FnMut(&'e Id, Loc)) {
+    crate::with_stacker(|| match e {
+        Expr::Fun { id, loc } => f(id, *loc),
+        Expr::Var { .. } | Expr::Int { .. } => {}
+        _ => {
+            let app = e.as_app().unwrap();
+            funs(&app.f, f);
+            funs(&app.arg, f);
+        }
+    })
+}
+
+// whether an identifier written `text` can be the function `name`: as it is,
+// unqualified in its module, or as the local name of a function lifted out
+// of another
+fn spells(name: &str, text: &str) -> bool {
+    let mut forms = vec![name];
+    if let Some((_, unqualified)) = name.split_once('.') {
+        forms.push(unqualified);
+    }
+    if let Some((_, local)) = name.rsplit_once(';') {
+        forms.push(local.rsplit_once('_').map_or(local, |(local, _)| local));
+    }
+    forms.iter().any(|form| literal::symbol(form) == text)
+}
+
+fn error(id: Json, code: i64, message: String) -> Json {
+    let error = Json::obj([
+        ("code", Json::Num(code as f64)),
+        ("message", message.into()),
+    ]);
+    Json::obj([("jsonrpc", "2.0".into()), ("id", id), ("error", error)])
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::obj([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::obj([("uri", uri.into()), ("diagnostics", Json::Arr(diagnostics))]),
+        ),
+    ])
+}
+
+fn uri_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok());
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(decoded) if b == b'%' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn path_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for c in path.display().to_string().chars() {
+        match c {
+            ' ' | '%' | '#' | '?' => uri.push_str(&format!("%{:02X}", c as u32)),
+            c => uri.push(c),
+        }
+    }
+    uri
+}
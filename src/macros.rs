@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::{
+    alloc::{Alloc, Id},
+    error::{Error, ErrorTy, Loc},
+    parser::Expr,
+    unify,
+};
+
+const MAX_DEPTH: usize = 64;
+
+// `macro pat => template;` rules of a file, by the name of the macro. they stay
+// in the file that declares them, as fixities do: templates are expanded
+// before a module's names are qualified, so a template taken into another
+// file would refer to functions that are not in scope there
+pub(crate) type Macros = HashMap<Id, Vec<(Expr, Expr)>>;
+
+// expands every macro use in `e`, including those a template introduces
+pub(crate) fn expand(alloc: &mut Alloc, macros: &Macros, e: &Expr) -> Result<Expr, Error> {
+    expand_at(alloc, macros, e, 0)
+}
+
+fn expand_at(alloc: &mut Alloc, macros: &Macros, e: &Expr, depth: usize) -> Result<Expr, Error> {
+    crate::with_stacker(|| {
+        let Some((name, rules)) = head(e).and_then(|id| macros.get_key_value(id)) else {
+            return Ok(match e.as_app() {
+                Some(app) => {
+                    let f = expand_at(alloc, macros, &app.f, depth)?;
+                    let arg = expand_at(alloc, macros, &app.arg, depth)?;
+                    Expr::app(f, arg, app.loc)
+                }
+                None => e.clone(),
+            });
+        };
+        let err = |desc| Error {
+            loc: e.loc(),
+            ty: ErrorTy::MacroError,
+            desc,
+        };
+        if depth >= MAX_DEPTH {
+            return Err(err(format!(
+                "expanding `{}` nested more than {MAX_DEPTH} macro uses",
+                alloc.get_string(name)
+            )));
+        }
+        for (pat, template) in rules {
+            let mut bindings = HashMap::new();
+            if unify::matches(&mut bindings, pat, e) {
+                let res = instantiate(alloc, &bindings, &mut HashMap::new(), template, e.loc());
+                return expand_at(alloc, macros, &res, depth + 1);
+            }
+        }
+        Err(err(format!(
+            "no rule of macro `{}` matches this use",
+            alloc.get_string(name)
+        )))
+    })
+}
+
+fn head(e: &Expr) -> Option<&Id> {
+    match e {
+        Expr::Fun { id, .. } => Some(id),
+        _ => head(&e.as_app()?.f),
+    }
+}
+
+// the template with the macro's variables replaced, every other variable
+// renamed apart from those at the use site, and every new term located at
+// the use site
+fn instantiate(
+    alloc: &mut Alloc,
+    bindings: &HashMap<&Id, &Expr>,
+    renamed: &mut HashMap<Id, Id>,
+    template: &Expr,
+    loc: Loc,
+) -> Expr {
+    crate::with_stacker(|| match template {
+        Expr::Var { id, .. } | Expr::Fun { id, .. } if bindings.contains_key(id) => {
+            bindings[id].clone()
+        }
+        Expr::Var { id, .. } if alloc.get_string(id) == "_" => Expr::Var {
+            id: id.clone(),
+            loc,
+        },
+        Expr::Var { id, .. } => {
+            let id = match renamed.get(id) {
+                Some(new) => new.clone(),
+                None => {
+                    let base = format!("{};m", alloc.get_string(id));
+                    let new = alloc.fresh(&base);
+                    renamed.insert(id.clone(), new.clone());
+                    new
+                }
+            };
+            Expr::Var { id, loc }
+        }
+        Expr::Fun { id, .. } => Expr::Fun {
+            id: id.clone(),
+            loc,
+        },
+        Expr::Int { n, .. } => Expr::Int { n: n.clone(), loc },
+        _ => {
+            let app = template.as_app().unwrap();
+            let f = instantiate(alloc, bindings, renamed, &app.f, loc);
+            let arg = instantiate(alloc, bindings, renamed, &app.arg, loc);
+            Expr::app(f, arg, loc)
+        }
+    })
+}
@@ -1,6 +1,6 @@
 use io::Write;
-use rhokell::{Alloc, DisplayWithAlloc, Rules};
-use std::{fs, io};
+use rhokell::{manifest::MANIFEST, Alloc, AttrArg, DisplayWithAlloc, Manifest, Rules, Style};
+use std::{fs, io, path::Path};
 
 enum Flag {
     Repl,
@@ -20,27 +20,60 @@ fn parse_flags(args: &[String]) -> (Flag, usize) {
 }
 
 fn main() {
-    let args: Vec<_> = std::env::args().collect();
+    let mut args: Vec<_> = std::env::args().collect();
+    if args.get(1).is_some_and(|a| a == "fmt") {
+        fmt(&args[2..]);
+    }
+    if args.get(1).is_some_and(|a| a == "doc") {
+        doc_file(&args[2..]);
+    }
+    if args.get(1).is_some_and(|a| a == "graph") {
+        graph(&args[2..]);
+    }
+    if args.get(1).is_some_and(|a| a == "lsp") {
+        if let Err(e) = rhokell::lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+            eprintln!("language server failed: {e}");
+            std::process::exit(-1);
+        }
+        return;
+    }
+    let prelude = args.iter().position(|a| a == "--prelude");
+    let prelude = prelude.map(|i| args.remove(i)).is_some();
+    let project = args.get(1).is_some_and(|a| a == "run");
+    if project {
+        args.remove(1);
+    }
     let (ty, idx) = parse_flags(&args);
     let mut alloc = Alloc::new();
 
-    let rules = rhokell::parse(
-        &mut alloc,
-        fs::read_to_string(
-            fs::canonicalize(args.get(idx).unwrap_or_else(|| help()))
-                .expect("could not canonicalize argument"),
-        )
-        .expect("could not read file"),
-    );
+    let (rules, main) = if project {
+        let manifest = load_manifest(&mut alloc);
+        let rules = rhokell::parse_project(&mut alloc, &manifest);
+        (rules, manifest.main)
+    } else {
+        let path = Path::new(args.get(idx).unwrap_or_else(|| help()));
+        let rules = if prelude {
+            rhokell::parse_file_with_prelude(&mut alloc, path)
+        } else {
+            rhokell::parse_file(&mut alloc, path)
+        };
+        (rules, "(main)".into())
+    };
     let rules = rules.unwrap_or_else(|e| {
-        e.report();
+        e.report(&alloc);
         std::process::exit(-1);
     });
+    for warning in alloc.take_warnings() {
+        warning.report(&alloc);
+    }
     //dbg!(&rules);
     match ty {
         Flag::Repl => repl(&mut alloc, &rules),
         _ => {
-            let mut expr = rhokell::parse_expr(&mut alloc, "(main)".into()).unwrap();
+            let mut expr = rhokell::parse_expr(&mut alloc, main).unwrap_or_else(|e| {
+                e.report(&alloc);
+                std::process::exit(-1);
+            });
             rhokell::apply(&rules, &mut expr, &mut alloc);
             if let Flag::Rd = ty {
                 println!("{}", expr.to_string(&alloc));
@@ -50,9 +83,10 @@ fn main() {
 }
 
 fn repl(alloc: &mut Alloc, rules: &Rules) {
-    println!("welcome to rhokell v0.2.0!\ninput `q`, `quit`, or `exit` for exiting the REPL");
+    println!("welcome to rhokell v0.2.0!\ninput `q`, `quit`, or `exit` for exiting the REPL\n`:solve <expr> = <expr>` searches for values of the variables in a goal\n`:literals` toggles showing byte lists as string literals\n`:numbers` toggles showing numerals as `#n`\n`:infix` toggles showing declared operators infix\n`:doc <name>` shows the documentation of a function");
 
     let mut line = String::new();
+    let mut style = Style::default();
 
     let mut prompt = |s| {
         print!("{s} ");
@@ -83,31 +117,225 @@ fn repl(alloc: &mut Alloc, rules: &Rules) {
             line.push_str(&t);
         }
 
+        if let Some(goal) = line.strip_prefix(":solve") {
+            solve(alloc, rules, goal);
+            continue;
+        }
+        if let Some(name) = line.strip_prefix(":doc") {
+            doc(alloc, rules, name.trim());
+            continue;
+        }
+        if line == ":literals" {
+            style.strings = !style.strings;
+            println!("string literals in output are {}", on_off(style.strings));
+            continue;
+        }
+        if line == ":infix" {
+            style.infix = !style.infix;
+            println!("infix operators in output are {}", on_off(style.infix));
+            continue;
+        }
+        if line == ":numbers" {
+            style.numbers = !style.numbers;
+            println!("numeric literals in output are {}", on_off(style.numbers));
+            continue;
+        }
+
         let expr = rhokell::parse_expr(alloc, line);
         let mut expr = match expr {
             Ok(x) => x,
             Err(e) => {
-                e.report();
+                e.report(alloc);
                 continue;
             }
         };
 
-        rhokell::apply(&rules, &mut expr, alloc);
+        rhokell::apply(rules, &mut expr, alloc);
 
-        println!("{}", expr.to_string(alloc));
+        let mut s = String::new();
+        expr.display_styled(alloc, style, &mut s);
+        println!("{s}");
     }
 }
 
+fn on_off(b: bool) -> &'static str {
+    if b {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn solve(alloc: &mut Alloc, rules: &Rules, goal: &str) {
+    let (lhs, rhs) = match rhokell::parse_goal(alloc, goal.into()) {
+        Ok(x) => x,
+        Err(e) => {
+            e.report(alloc);
+            return;
+        }
+    };
+
+    let solutions = rhokell::solve(rules, alloc, &lhs, &rhs, 8);
+    if solutions.is_empty() {
+        println!("no solutions found");
+    }
+    for s in solutions {
+        println!("{}", s.to_string(alloc));
+    }
+}
+
+fn load_manifest(alloc: &mut Alloc) -> Manifest {
+    let dir = std::env::current_dir().expect("could not get current directory");
+    let path = Manifest::find(&dir).unwrap_or_else(|| {
+        eprintln!("could not find `{MANIFEST}` in this directory or any parent");
+        std::process::exit(-1);
+    });
+    Manifest::load(alloc, &path).unwrap_or_else(|e| {
+        e.report(alloc);
+        std::process::exit(-1);
+    })
+}
+
+// rewrites the files formatted, or with `--check` lists those that are not
+fn fmt(args: &[String]) -> ! {
+    let check = args.iter().any(|a| a == "--check");
+    let paths: Vec<_> = args.iter().filter(|a| *a != "--check").collect();
+    if paths.is_empty() {
+        help();
+    }
+    let mut unformatted = false;
+    for path in paths {
+        let fail = |desc: String| -> ! {
+            eprintln!("{desc}");
+            std::process::exit(-1);
+        };
+        if path.ends_with(".rhk.md") {
+            fail(format!(
+                "`{path}` is a literate file, which `fmt` leaves alone"
+            ));
+        }
+        let src = fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(format!("could not read `{path}`: {e}")));
+        let mut alloc = Alloc::new();
+        let file = alloc.add_file(Path::new(path));
+        let formatted = rhokell::format::format(&mut alloc, &src, file).unwrap_or_else(|e| {
+            e.report(&alloc);
+            std::process::exit(-1);
+        });
+        if formatted == src {
+            continue;
+        }
+        if check {
+            println!("{path} is not formatted");
+            unformatted = true;
+        } else if let Err(e) = fs::write(path, formatted) {
+            fail(format!("could not write `{path}`: {e}"));
+        }
+    }
+    std::process::exit(i32::from(unformatted))
+}
+
+// the one file among the arguments of `doc` or `graph`, loaded
+fn load_tool_input(args: &[String], prelude: bool) -> (&Path, Alloc, Rules) {
+    let paths: Vec<_> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    let [path] = paths[..] else { help() };
+    let path = Path::new(path);
+    let mut alloc = Alloc::new();
+    let rules = if prelude {
+        rhokell::parse_file_with_prelude(&mut alloc, path)
+    } else {
+        rhokell::parse_file(&mut alloc, path)
+    };
+    let rules = rules.unwrap_or_else(|e| {
+        e.report(&alloc);
+        std::process::exit(-1);
+    });
+    for warning in alloc.take_warnings() {
+        warning.report(&alloc);
+    }
+    (path, alloc, rules)
+}
+
+// prints the reference of a program as HTML, or with `--markdown` as Markdown
+fn doc_file(args: &[String]) -> ! {
+    let flag = |name: &str| args.iter().any(|a| a == name);
+    let (markdown, prelude) = (flag("--markdown"), flag("--prelude"));
+    let (path, alloc, rules) = load_tool_input(args, prelude);
+    let title = path
+        .file_stem()
+        .map_or("rhokell".into(), |s| s.to_string_lossy());
+    if markdown {
+        print!("{}", rhokell::doc::markdown(&alloc, &rules, &title));
+    } else {
+        print!("{}", rhokell::doc::html(&alloc, &rules, &title));
+    }
+    std::process::exit(0)
+}
+
+// reports the mutual recursion in a program and the functions `main` never
+// reaches, or with `--dot` or `--json` prints its call graph
+fn graph(args: &[String]) -> ! {
+    let flag = |name: &str| args.iter().any(|a| a == name);
+    let (_, alloc, rules) = load_tool_input(args, flag("--prelude"));
+    let graph = rhokell::graph::CallGraph::new(&alloc, &rules);
+    if flag("--dot") {
+        print!("{}", graph.dot());
+    } else if flag("--json") {
+        print!("{}", graph.json("main"));
+    } else {
+        let names = |vs: &[usize]| {
+            let names: Vec<_> = vs.iter().map(|&v| &graph.names[v][..]).collect();
+            names.join(", ")
+        };
+        let sccs = graph.mutual_recursion();
+        if sccs.is_empty() {
+            println!("no mutual recursion");
+        }
+        for scc in sccs {
+            println!("mutual recursion: {}", names(&scc));
+        }
+        match graph.unreachable("main") {
+            None => println!("no `main` to start from"),
+            Some(vs) if vs.is_empty() => println!("every function is reachable from `main`"),
+            Some(vs) => println!("unreachable from `main`: {}", names(&vs)),
+        }
+    }
+    std::process::exit(0)
+}
+
 fn help() -> ! {
+    let exe = std::env::current_exe().unwrap_or_else(|_| "rhokell".into());
     println!(
-        "usage: {} [-r|-d] <filename>",
-        std::env::current_exe()
-            .unwrap_or_else(|_| "rhokell".into())
-            .display()
+        "usage: {0} [--prelude] [-r|-d] <filename>\n       {0} run [-r|-d]\n       {0} fmt [--check] <filename>...\n       {0} doc [--markdown] [--prelude] <filename>\n       {0} graph [--dot|--json] [--prelude] <filename>\n       {0} lsp",
+        exe.display()
     );
     std::process::exit(-1);
 }
 
+fn doc(alloc: &mut Alloc, rules: &Rules, name: &str) {
+    let id = alloc.alloc_or_get(name);
+    let mut docs: Vec<&str> = Vec::new();
+    for def in rules.get(&id).into_iter().flatten() {
+        for attr in def
+            .attrs
+            .iter()
+            .filter(|a| alloc.get_string(&a.name) == "doc")
+        {
+            if let [AttrArg::Str(text)] = &attr.args[..] {
+                if !docs.contains(&&text[..]) {
+                    docs.push(text);
+                }
+            }
+        }
+    }
+    if docs.is_empty() {
+        println!("no documentation for `{name}`");
+    }
+    for text in docs {
+        println!("{text}");
+    }
+}
+
 fn is_quit(s: &str) -> bool {
     ["quit", "exit", "q"].contains(&s)
 }
@@ -0,0 +1,192 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    alloc::Alloc,
+    error::{Error, ErrorTy, Loc},
+};
+
+pub const MANIFEST: &str = "rhokell.toml";
+
+// the `[project]` table of a `rhokell.toml`, with paths made absolute
+#[derive(Clone, Debug)]
+pub struct Manifest {
+    pub entry: PathBuf,
+    // the expression `rhokell run` evaluates
+    pub main: String,
+    pub src: Vec<PathBuf>,
+    pub lib: Vec<PathBuf>,
+    pub prelude: bool,
+}
+
+enum Value {
+    Str(String),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+impl Manifest {
+    // the nearest manifest in `dir` or one of its ancestors
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|d| d.join(MANIFEST))
+            .find(|p| p.is_file())
+    }
+
+    pub fn load(alloc: &mut Alloc, path: &Path) -> Result<Manifest, Error> {
+        let src = fs::read_to_string(path).map_err(|e| Error {
+            loc: Loc::new(),
+            ty: ErrorTy::ManifestError,
+            desc: format!("could not read `{}`: {e}", path.display()),
+        })?;
+        Manifest::parse(alloc, &src, path)
+    }
+
+    // the manifest `src`, read from `path`
+    pub fn parse(alloc: &mut Alloc, src: &str, path: &Path) -> Result<Manifest, Error> {
+        let file = alloc.add_file(path);
+        let err = |line, desc| Error {
+            loc: Loc { line, col: 1, file },
+            ty: ErrorTy::ManifestError,
+            desc,
+        };
+        let root = path.parent().unwrap_or(Path::new("."));
+
+        let mut manifest = Manifest {
+            entry: root.join("main.rhk"),
+            main: "(main)".into(),
+            src: Vec::new(),
+            lib: Vec::new(),
+            prelude: false,
+        };
+        let mut section = String::new();
+        for (i, line) in src.lines().enumerate() {
+            let line_no = i as u64 + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                if section != "project" {
+                    return Err(err(line_no, format!("unknown section `[{section}]`")));
+                }
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| err(line_no, "expected `key = value`".into()))?;
+            let value = parse_value(value.trim())
+                .ok_or_else(|| err(line_no, format!("invalid value `{}`", value.trim())))?;
+            if section.is_empty() {
+                return Err(err(
+                    line_no,
+                    "expected `[project]` before the first key".into(),
+                ));
+            }
+
+            match (key.trim(), value) {
+                ("entry", Value::Str(s)) => manifest.entry = root.join(s),
+                ("main", Value::Str(s)) => manifest.main = s,
+                ("src", Value::List(l)) => manifest.src = l.iter().map(|p| root.join(p)).collect(),
+                ("lib", Value::List(l)) => manifest.lib = l.iter().map(|p| root.join(p)).collect(),
+                ("prelude", Value::Bool(b)) => manifest.prelude = b,
+                (key, _) => {
+                    return Err(err(
+                        line_no,
+                        format!("unknown key `{key}`, or a value of the wrong type for it"),
+                    ))
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    // where imports are looked up when not found next to the importing file
+    pub fn search_path(&self) -> Vec<PathBuf> {
+        self.src.iter().chain(&self.lib).cloned().collect()
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_str => escaped = true,
+            '"' => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(s: &str) -> Option<Value> {
+    match s {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        _ => {}
+    }
+    if let Some(items) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        return parse_list(items).map(Value::List);
+    }
+    parse_str(s).map(Value::Str)
+}
+
+// the strings of a list, separated by commas with an optional trailing one
+fn parse_list(s: &str) -> Option<Vec<String>> {
+    let mut items = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let len = str_len(rest)?;
+        items.push(parse_str(&rest[..len])?);
+        rest = rest[len..].trim_start();
+        match rest.strip_prefix(',') {
+            Some(after) => rest = after.trim_start(),
+            None if rest.is_empty() => {}
+            None => return None,
+        }
+    }
+    Some(items)
+}
+
+// the length of the quoted string `s` starts with
+fn str_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    if chars.next()?.1 != '"' {
+        return None;
+    }
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next()?;
+            }
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_str(s: &str) -> Option<String> {
+    let s = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut res = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        res.push(match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                c @ ('\\' | '"') => c,
+                _ => return None,
+            },
+            '"' => return None,
+            c => c,
+        });
+    }
+    Some(res)
+}
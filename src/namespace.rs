@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    alloc::{Alloc, Id},
+    error::{Error, ErrorTy},
+    parser::{App, Def, Expr},
+    Rules,
+};
+
+// renames every function defined in a module to `module.name`, both where it
+// is defined and where the module refers to it, so qualified names are plain
+// `Id`s by the time anything is evaluated
+pub(crate) fn qualify(
+    alloc: &mut Alloc,
+    module: &Id,
+    defs: Vec<Def>,
+) -> (Vec<Def>, HashMap<Id, Id>) {
+    let map: HashMap<_, _> = defs
+        .iter()
+        .map(|def| (def.id.clone(), alloc.qualify(module, &def.id)))
+        .collect();
+    let defs = defs
+        .into_iter()
+        .map(|def| {
+            // a function symbol that is bound as a variable stays a variable
+            let mut bound = HashSet::new();
+            crate::vars(&mut bound, &def.pat);
+            for (_, nf) in &def.guards {
+                crate::vars(&mut bound, nf);
+            }
+            Def {
+                id: map[&def.id].clone(),
+                loc: def.loc,
+                pat: resolve(&map, &bound, &def.pat),
+                rep: resolve(&map, &bound, &def.rep),
+                guards: def
+                    .guards
+                    .iter()
+                    .map(|(g, nf)| (resolve(&map, &bound, g), resolve(&map, &bound, nf)))
+                    .collect(),
+                attrs: def.attrs,
+            }
+        })
+        .collect();
+    (defs, map)
+}
+
+fn resolve(map: &HashMap<Id, Id>, bound: &HashSet<Id>, e: &Expr) -> Expr {
+    crate::with_stacker(|| match e {
+        Expr::Fun { id, loc } if map.contains_key(id) && !bound.contains(id) => Expr::Fun {
+            id: map[id].clone(),
+            loc: *loc,
+        },
+        Expr::Fun { .. } | Expr::Var { .. } | Expr::Int { .. } => e.clone(),
+        _ => {
+            let f = e.as_app().unwrap();
+            let new_f = resolve(map, bound, &f.f);
+            Expr::App(Box::new(App {
+                id: map
+                    .get(&f.id)
+                    .filter(|_| !bound.contains(&f.id))
+                    .unwrap_or(&f.id)
+                    .clone(),
+                loc: f.loc,
+                f: new_f,
+                arg: resolve(map, bound, &f.arg),
+            }))
+        }
+    })
+}
+
+// fails if a definition outside a module mentions one of its private functions.
+// `private` maps qualified names to their module, `modules` files to theirs
+pub(crate) fn check_private(
+    alloc: &Alloc,
+    defs: &Rules,
+    private: &HashMap<Id, Id>,
+    modules: &HashMap<usize, Id>,
+) -> Result<(), Error> {
+    for def in defs.values().flatten() {
+        let module = modules.get(&def.loc.file);
+        let exprs = [&def.pat, &def.rep]
+            .into_iter()
+            .chain(def.guards.iter().flat_map(|(g, nf)| [g, nf]));
+        for e in exprs {
+            check_expr(alloc, private, module, e)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_expr(
+    alloc: &Alloc,
+    private: &HashMap<Id, Id>,
+    module: Option<&Id>,
+    e: &Expr,
+) -> Result<(), Error> {
+    match e {
+        Expr::Fun { id, loc } => match private.get(id) {
+            Some(owner) if Some(owner) != module => Err(Error {
+                loc: *loc,
+                ty: ErrorTy::ScopeError,
+                desc: format!(
+                    "`{}` is private to module `{}`",
+                    alloc.get_string(id),
+                    alloc.get_string(owner)
+                ),
+            }),
+            _ => Ok(()),
+        },
+        Expr::Var { .. } | Expr::Int { .. } => Ok(()),
+        _ => {
+            let f = e.as_app().unwrap();
+            check_expr(alloc, private, module, &f.f)?;
+            check_expr(alloc, private, module, &f.arg)
+        }
+    }
+}
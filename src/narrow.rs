@@ -0,0 +1,216 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    alloc::{Alloc, DisplayWithAlloc, Id},
+    parser::{App, Def, Expr},
+    unify::{self, unify, Subst},
+    Rules,
+};
+
+const MAX_DEPTH: usize = 16;
+const MAX_STATES: usize = 20_000;
+
+pub struct Solution(Vec<(Id, Expr)>);
+
+impl DisplayWithAlloc for Solution {
+    fn display(&self, alloc: &Alloc, s: &mut String) {
+        if self.0.is_empty() {
+            s.push_str("yes");
+        }
+        for (i, (id, e)) in self.0.iter().enumerate() {
+            if i > 0 {
+                s.push_str(", ");
+            }
+            s.push_str(alloc.get_string(id));
+            s.push_str(" = ");
+            e.display(alloc, s);
+        }
+    }
+}
+
+type Step = (Expr, Subst, Vec<(Expr, Expr)>);
+
+struct State {
+    // guards of the rules used so far become extra equations, conjoined
+    // with the goal under the `;` constructor which no source can name
+    lhs: Expr,
+    rhs: Expr,
+    // current instantiation of each goal variable
+    bindings: Vec<(Id, Expr)>,
+    depth: usize,
+}
+
+// breadth-first narrowing of `lhs = rhs`, yielding at most `max` solutions
+pub fn solve(defs: &Rules, alloc: &mut Alloc, lhs: &Expr, rhs: &Expr, max: usize) -> Vec<Solution> {
+    let mut goal_vars = Vec::new();
+    ordered_vars(&mut goal_vars, lhs);
+    ordered_vars(&mut goal_vars, rhs);
+
+    let mut queue = VecDeque::from([State {
+        lhs: lhs.clone(),
+        rhs: rhs.clone(),
+        bindings: goal_vars
+            .into_iter()
+            .map(|(id, loc)| (id.clone(), Expr::Var { id, loc }))
+            .collect(),
+        depth: 0,
+    }]);
+    let mut seen = HashSet::new();
+    let mut res = Vec::new();
+    let mut explored = 0;
+
+    while let Some(state) = queue.pop_front() {
+        if res.len() >= max || explored >= MAX_STATES {
+            break;
+        }
+        explored += 1;
+
+        if let Some(s) = unify(&state.lhs, &state.rhs) {
+            let sol = Solution(
+                state
+                    .bindings
+                    .iter()
+                    .map(|(id, e)| (id.clone(), s.apply(e)))
+                    .collect(),
+            );
+            if seen.insert(sol.to_string(alloc)) {
+                res.push(sol);
+            }
+        }
+        if state.depth >= MAX_DEPTH {
+            continue;
+        }
+
+        let mut steps = Vec::new();
+        narrow(defs, alloc, state.depth, &state.lhs, &mut steps);
+        let n = steps.len();
+        narrow(defs, alloc, state.depth, &state.rhs, &mut steps);
+        for (i, (e, s, guards)) in steps.into_iter().enumerate() {
+            let (mut lhs, mut rhs) = if i < n {
+                (e, state.rhs.clone())
+            } else {
+                (state.lhs.clone(), e)
+            };
+            for (guard, nf) in guards {
+                lhs = conj(alloc, lhs, guard);
+                rhs = conj(alloc, rhs, nf);
+            }
+            queue.push_back(State {
+                lhs: s.apply(&lhs),
+                rhs: s.apply(&rhs),
+                bindings: state
+                    .bindings
+                    .iter()
+                    .map(|(id, e)| (id.clone(), s.apply(e)))
+                    .collect(),
+                depth: state.depth + 1,
+            });
+        }
+    }
+    res
+}
+
+// every one-step narrowing of `e`, with the unifier still to be applied
+fn narrow(defs: &Rules, alloc: &mut Alloc, depth: usize, e: &Expr, out: &mut Vec<Step>) {
+    crate::with_stacker(|| {
+        if let Some(rules) = head(e).and_then(|id| defs.get(id)) {
+            for def in rules {
+                let (pat, rep, guards) = rename_apart(alloc, depth, def);
+                if let Some(s) = unify(&pat, e) {
+                    out.push((rep, s, guards));
+                }
+            }
+        }
+
+        if let Some(f) = e.as_app() {
+            let mut sub = Vec::new();
+            narrow(defs, alloc, depth, &f.f, &mut sub);
+            let n = sub.len();
+            narrow(defs, alloc, depth, &f.arg, &mut sub);
+            for (i, (e, s, guards)) in sub.into_iter().enumerate() {
+                let (new_f, arg) = if i < n {
+                    (e, f.arg.clone())
+                } else {
+                    (f.f.clone(), e)
+                };
+                out.push((
+                    Expr::App(Box::new(App {
+                        id: f.id.clone(),
+                        loc: f.loc,
+                        f: new_f,
+                        arg,
+                    })),
+                    s,
+                    guards,
+                ));
+            }
+        }
+    })
+}
+
+fn head(e: &Expr) -> Option<&Id> {
+    match e {
+        Expr::Fun { id, .. } => Some(id),
+        Expr::Var { .. } | Expr::Int { .. } => None,
+        _ => head(&e.as_app().unwrap().f),
+    }
+}
+
+// a copy of the rule with its variables replaced by ones unique to this depth,
+// which cannot clash with the variables the goal picked up from earlier steps,
+// nor, with the `;` no source can write, with those of the goal itself
+fn rename_apart(alloc: &mut Alloc, depth: usize, def: &Def) -> (Expr, Expr, Vec<(Expr, Expr)>) {
+    let mut pat_vars = HashSet::new();
+    crate::vars(&mut pat_vars, &def.pat);
+    for (_, nf) in &def.guards {
+        crate::vars(&mut pat_vars, nf);
+    }
+    let map: HashMap<_, _> = pat_vars
+        .into_iter()
+        .map(|id| {
+            let name = format!("{};{depth}", alloc.get_string(&id));
+            (id, alloc.alloc_or_get(&name))
+        })
+        .collect();
+    let guards = def
+        .guards
+        .iter()
+        .map(|(g, nf)| (unify::rename(&map, g), unify::rename(&map, nf)))
+        .collect();
+    (
+        unify::rename(&map, &def.pat),
+        unify::rename(&map, &def.rep),
+        guards,
+    )
+}
+
+fn conj(alloc: &mut Alloc, a: Expr, b: Expr) -> Expr {
+    let id = alloc.alloc_or_get(";");
+    let loc = a.loc();
+    let f = Expr::App(Box::new(App {
+        id: id.clone(),
+        loc,
+        f: Expr::Fun {
+            id: id.clone(),
+            loc,
+        },
+        arg: a,
+    }));
+    Expr::App(Box::new(App { id, loc, f, arg: b }))
+}
+
+fn ordered_vars(v: &mut Vec<(Id, crate::error::Loc)>, e: &Expr) {
+    match e {
+        Expr::Var { id, loc } => {
+            if !v.iter().any(|(x, _)| x == id) {
+                v.push((id.clone(), *loc));
+            }
+        }
+        Expr::Fun { .. } | Expr::Int { .. } => {}
+        _ => {
+            let f = e.as_app().unwrap();
+            ordered_vars(v, &f.f);
+            ordered_vars(v, &f.arg);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::rc::Rc;
+
+use crate::{
+    alloc::{Alloc, Id},
+    int::Int,
+    literal::{self, Numerals},
+    parser::Expr,
+};
+
+// built-in rules for native integers, tried when no definition applies:
+// `+ - * div mod` (rounding towards zero), the comparisons `lt le gt ge eq ne` giving `(t)` or `(f)`,
+// and `to_peano from_peano to_byte from_byte`
+pub(crate) fn reduce(alloc: &mut Alloc, e: &mut Expr) -> bool {
+    let Some(res) = eval(alloc, e) else {
+        return false;
+    };
+    *e = res;
+    true
+}
+
+fn eval(alloc: &mut Alloc, e: &Expr) -> Option<Expr> {
+    let loc = e.loc();
+    let (head, args) = spine(e)?;
+    let int = |n: Int| Some(Expr::Int { n: Rc::new(n), loc });
+    let name = alloc.get_string(&head).to_string();
+
+    match (&name[..], &args[..]) {
+        (op, [Expr::Int { n: a, .. }, Expr::Int { n: b, .. }]) => {
+            let cmp = match op {
+                "+" => return int(&**a + b),
+                "-" => return int(&**a - b),
+                "*" => return int(&**a * b),
+                "div" => return int(a.div_rem(b)?.0),
+                "mod" => return int(a.div_rem(b)?.1),
+                "lt" => a < b,
+                "le" => a <= b,
+                "gt" => a > b,
+                "ge" => a >= b,
+                "eq" => a == b,
+                "ne" => a != b,
+                _ => return None,
+            };
+            let id = alloc.alloc_or_get(if cmp { "t" } else { "f" });
+            Some(Expr::Fun { id, loc })
+        }
+        ("to_peano", [Expr::Int { n, .. }]) => {
+            Some(Numerals::unary(alloc).encode(n.to_u64()?, loc))
+        }
+        ("to_byte", [Expr::Int { n, .. }]) => {
+            let b = u8::try_from(n.to_u64()?).ok()?;
+            Some(literal::byte(alloc, b, loc))
+        }
+        ("from_peano", [p]) => int(literal::decode_unary(alloc, p)?.into()),
+        ("from_byte", [b]) => int((literal::decode_byte(alloc, b)? as u64).into()),
+        _ => None,
+    }
+}
+
+// the function symbol an application starts with, and its arguments
+fn spine(mut e: &Expr) -> Option<(Id, Vec<&Expr>)> {
+    let mut args = Vec::new();
+    while let Some(app) = e.as_app() {
+        args.push(&app.arg);
+        e = &app.f;
+    }
+    args.reverse();
+    match e {
+        Expr::Fun { id, .. } => Some((id.clone(), args)),
+        _ => None,
+    }
+}
@@ -1,8 +1,16 @@
-use std::rc::Rc;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
 
 use crate::{
-    alloc::{Alloc, DisplayWithAlloc, Id},
+    alloc::{Alloc, DisplayWithAlloc, Id, BYTE, INT},
     error::{Error, ErrorTy, Loc},
+    int::Int,
+    lift,
+    literal::{self, Numerals},
+    macros::{self, Macros},
+    pattern,
     token::{Scanner, TokenTy},
 };
 
@@ -12,12 +20,53 @@ pub struct Def {
     pub loc: Loc,
     pub(crate) pat: Expr,
     pub(crate) rep: Expr,
+    // `if guard => nf` clauses, checked in order after the pattern matched
+    pub(crate) guards: Vec<(Expr, Expr)>,
+    // `@name args` written before the definition
+    pub attrs: Vec<Attr>,
+}
+
+// an attribute of a definition, such as `@trace` or `@doc "..."`. unknown
+// ones are kept, with a warning
+#[derive(Clone, Debug)]
+pub struct Attr {
+    pub name: Id,
+    pub loc: Loc,
+    pub args: Vec<AttrArg>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttrArg {
+    Str(String),
+    Num(u64),
+}
+
+// `##` comments before a definition, as a `@doc` attribute before the others
+fn with_docs(alloc: &mut Alloc, docs: Vec<(Loc, String)>, mut attrs: Vec<Attr>) -> Vec<Attr> {
+    if let Some((loc, _)) = docs.first() {
+        let lines: Vec<_> = docs.iter().map(|(_, line)| &line[..]).collect();
+        attrs.insert(
+            0,
+            Attr {
+                name: alloc.alloc_or_get("doc"),
+                loc: *loc,
+                args: vec![AttrArg::Str(lines.join("\n"))],
+            },
+        );
+    }
+    attrs
 }
 
+// the attributes understood, and how many arguments each takes
+const ATTRS: &[(&str, std::ops::RangeInclusive<usize>)] =
+    &[("doc", 1..=1), ("deprecated", 0..=1), ("trace", 0..=0)];
+
 #[derive(Clone, Debug)]
 pub enum Expr {
     Fun { id: Id, loc: Loc },
     Var { id: Id, loc: Loc },
+    // native integer, written `#n` under `pragma numerals native;`
+    Int { n: Rc<Int>, loc: Loc },
     // reduced function
     RedApp(Rc<App>),
     // unreduced function
@@ -35,15 +84,61 @@ pub struct App {
 impl Expr {
     pub(crate) fn loc(&self) -> Loc {
         match self {
-            Expr::Var { loc, .. } | Expr::Fun { loc, .. } => *loc,
+            Expr::Var { loc, .. } | Expr::Fun { loc, .. } | Expr::Int { loc, .. } => *loc,
             Expr::App(f) => f.loc,
             Expr::RedApp(f) => f.loc,
         }
     }
 
-    fn display_internal(&self, alloc: &Alloc, s: &mut String, parens: bool) {
+    pub(crate) fn app(f: Expr, arg: Expr, loc: Loc) -> Expr {
+        let id = head_id(&f);
+        Expr::App(Box::new(App { id, loc, f, arg }))
+    }
+
+    pub(crate) fn as_app(&self) -> Option<&App> {
+        match self {
+            Expr::App(f) => Some(f),
+            Expr::RedApp(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn display_styled(&self, alloc: &Alloc, style: Style, s: &mut String) {
+        self.display_internal(alloc, style, s, true)
+    }
+
+    fn display_internal(&self, alloc: &Alloc, style: Style, s: &mut String, parens: bool) {
         crate::with_stacker(|| {
-            let parens = parens && !matches!(self, Expr::Var { .. });
+            if style.strings {
+                if let Some(text) = literal::decode_string(alloc, self) {
+                    return s.push_str(&literal::escape(&text, '"'));
+                }
+                if let Some(b) = literal::decode_byte(alloc, self).filter(u8::is_ascii) {
+                    return s.push_str(&literal::escape(&(b as char).to_string(), '\''));
+                }
+            }
+            if style.numbers {
+                if let Some(n) = literal::decode_nat(alloc, self) {
+                    return s.push_str(&format!("#{n}"));
+                }
+            }
+            if style.infix {
+                if let Some((op, fixity, a, b)) = as_infix(alloc, self) {
+                    if parens {
+                        s.push('(');
+                    }
+                    a.display_operand(alloc, style, s, fixity, Assoc::Left);
+                    s.push(' ');
+                    s.push_str(&literal::symbol(alloc.get_string(op)));
+                    s.push(' ');
+                    b.display_operand(alloc, style, s, fixity, Assoc::Right);
+                    if parens {
+                        s.push(')');
+                    }
+                    return;
+                }
+            }
+            let parens = parens && !matches!(self, Expr::Var { .. } | Expr::Int { .. });
             if parens {
                 s.push('(');
             }
@@ -51,24 +146,37 @@ impl Expr {
             match self {
                 Expr::RedApp(fun) => {
                     let App { f, arg, .. } = &**fun;
-                    f.display_internal(alloc, s, false);
+                    f.display_internal(alloc, style, s, false);
                     s.push(' ');
-                    arg.display_internal(alloc, s, true)
+                    arg.display_internal(alloc, style, s, true)
                 }
                 Expr::App(fun) => {
                     let App { f, arg, .. } = &**fun;
-                    f.display_internal(alloc, s, false);
+                    f.display_internal(alloc, style, s, false);
                     s.push(' ');
-                    arg.display_internal(alloc, s, true)
+                    arg.display_internal(alloc, style, s, true)
                 }
-                Expr::Var { id, .. } => s.push_str(alloc.get_string(id)),
-                Expr::Fun { id, .. } => s.push_str(alloc.get_string(id)),
+                Expr::Var { id, .. } => s.push_str(&literal::symbol(alloc.get_string(id))),
+                Expr::Fun { id, .. } => s.push_str(&literal::symbol(alloc.get_string(id))),
+                Expr::Int { n, .. } => s.push_str(&format!("#{n}")),
             }
             if parens {
                 s.push(')');
             }
         })
     }
+
+    fn display_operand(
+        &self,
+        alloc: &Alloc,
+        style: Style,
+        s: &mut String,
+        op: Fixity,
+        side: Assoc,
+    ) {
+        let bare = as_infix(alloc, self).is_some_and(|(_, inner, ..)| op.binds(inner, side));
+        self.display_internal(alloc, style, s, !bare)
+    }
 }
 
 impl Default for Expr {
@@ -85,6 +193,7 @@ impl PartialEq for Expr {
         match (self, other) {
             (Expr::Var { id, .. }, Expr::Var { id: id2, .. }) => id == id2,
             (Expr::Fun { id, .. }, Expr::Fun { id: id2, .. }) => id == id2,
+            (Expr::Int { n, .. }, Expr::Int { n: n2, .. }) => n == n2,
             (Expr::RedApp(f1), Expr::RedApp(f2)) => f1 == f2,
             (Expr::App(f1), Expr::App(f2)) => f1 == f2,
             _ => false,
@@ -94,10 +203,61 @@ impl PartialEq for Expr {
 
 impl DisplayWithAlloc for Expr {
     fn display(&self, alloc: &Alloc, s: &mut String) {
-        self.display_internal(alloc, s, true)
+        self.display_styled(alloc, Style::default(), s)
     }
 }
 
+fn head_id(e: &Expr) -> Id {
+    match e {
+        Expr::Fun { id, .. } | Expr::Var { id, .. } => id.clone(),
+        Expr::Int { .. } => INT,
+        _ => e.as_app().unwrap().id.clone(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Assoc {
+    Left,
+    Right,
+    None,
+}
+
+// how an infix operator binds, from `infixl 6 +;` and the like
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Fixity {
+    pub(crate) assoc: Assoc,
+    pub(crate) prec: u8,
+}
+
+impl Fixity {
+    // whether an operand with fixity `inner`, on the `side` of an operator
+    // with this fixity, needs no parentheses
+    fn binds(self, inner: Fixity, side: Assoc) -> bool {
+        inner.prec > self.prec || (inner == self && self.assoc == side)
+    }
+}
+
+// `(a op b)` for an operator with a declared fixity
+fn as_infix<'e>(alloc: &Alloc, e: &'e Expr) -> Option<(&'e Id, Fixity, &'e Expr, &'e Expr)> {
+    let outer = e.as_app()?;
+    let inner = outer.f.as_app()?;
+    match &inner.f {
+        Expr::Fun { id, .. } => Some((id, alloc.fixity(id)?, &inner.arg, &outer.arg)),
+        _ => None,
+    }
+}
+
+// optional sugar for `Expr::display_styled`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Style {
+    // render byte lists as "strings" and lone bytes as 'c'haracters
+    pub strings: bool,
+    // render `s`/`z` and `e`/`o`/`i` numerals as `#n`
+    pub numbers: bool,
+    // render applications of declared operators as `(a + b)`
+    pub infix: bool,
+}
+
 impl PartialEq for App {
     fn eq(&self, other: &Self) -> bool {
         self.f == other.f && self.arg == other.arg
@@ -114,20 +274,177 @@ impl Drop for App {
     }
 }
 
+pub enum Item {
+    Def(Def),
+    // `import "path";`, with the path as written
+    Import(Loc, String),
+}
+
 pub struct Parser<'a> {
     pub(crate) sc: Scanner<'a>,
+    // definitions produced by desugaring but not yet handed out
+    pub(crate) pending: VecDeque<Def>,
+    // set by `pragma dispatch specific;`
+    pub(crate) specific: bool,
+    // constructors for list and string literals, set by `pragma list cons nil;`
+    cons: Id,
+    nil: Id,
+    // spelling of `#n`, set by `pragma numerals unary|binary|native;`
+    numerals: Numerals,
+    // the definition being parsed, the variables in scope in its replacement,
+    // and the anonymous functions lifted out of it so far
+    owner: Id,
+    scope: Vec<Id>,
+    lambdas: Vec<Def>,
+    macros: Macros,
+    // operators declared with `infixl`, `infixr` or `infix`, which like macros
+    // only apply to the file that declares them
+    fixities: HashMap<Id, Fixity>,
+    // set by `module name;`, which must come before any definition
+    pub(crate) module: Option<Id>,
+    // functions marked `private`, by their unqualified names
+    pub(crate) private: HashSet<Id>,
+    seen_def: bool,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(sc: Scanner<'a>) -> Self {
-        Self { sc }
+    pub fn new(sc: Scanner<'a>, alloc: &mut Alloc) -> Self {
+        Self {
+            sc,
+            pending: VecDeque::new(),
+            specific: false,
+            cons: alloc.alloc_or_get("cons"),
+            nil: alloc.alloc_or_get("nil"),
+            numerals: Numerals::unary(alloc),
+            owner: alloc.alloc_or_get("lambda"),
+            scope: Vec::new(),
+            lambdas: Vec::new(),
+            macros: Macros::new(),
+            fixities: HashMap::new(),
+            module: None,
+            private: HashSet::new(),
+            seen_def: false,
+        }
+    }
+    pub fn parse_item(&mut self, alloc: &mut Alloc) -> Result<Option<Item>, Error> {
+        if let Some(def) = self.pending.pop_front() {
+            return Ok(Some(Item::Def(def)));
+        }
+        let mut attrs = Vec::new();
+        let mut docs = Vec::new();
+        loop {
+            if attrs.is_empty() {
+                docs = self.sc.docs(alloc)?;
+            }
+            let kw = match self.sc.peek(alloc)?.ty() {
+                TokenTy::Eof if attrs.is_empty() => return Ok(None),
+                TokenTy::Ident(kw) => alloc.get_string(&kw).to_string(),
+                _ => break,
+            };
+            match &*kw {
+                _ if kw.len() > 1 && kw.starts_with('@') => attrs.push(self.parse_attr(alloc)?),
+                "pragma" | "import" | "module" | "infixl" | "infixr" | "infix" | "macro"
+                    if !attrs.is_empty() =>
+                {
+                    return Err(Error {
+                        loc: self.sc.peek(alloc)?.loc(),
+                        ty: ErrorTy::SyntaxError,
+                        desc: "attributes must be followed by a definition".into(),
+                    });
+                }
+                "pragma" => self.parse_pragma(alloc)?,
+                "import" => {
+                    self.sc.expect_identifier(alloc)?;
+                    let (loc, path) = self.sc.expect_string(alloc)?;
+                    self.sc.expect_token(alloc, TokenTy::Semi)?;
+                    return Ok(Some(Item::Import(loc, path)));
+                }
+                "module" => {
+                    let (loc, _) = self.sc.expect_identifier(alloc)?;
+                    if self.module.is_some() || self.seen_def {
+                        return Err(Error {
+                            loc,
+                            ty: ErrorTy::SyntaxError,
+                            desc: "a module declaration must come before all definitions".into(),
+                        });
+                    }
+                    self.module = Some(self.sc.expect_identifier(alloc)?.1);
+                    self.sc.expect_token(alloc, TokenTy::Semi)?;
+                }
+                "infixl" | "infixr" | "infix" => self.parse_fixity(alloc)?,
+                "macro" => self.parse_macro(alloc)?,
+                "private" => {
+                    self.sc.expect_identifier(alloc)?;
+                    let attrs = with_docs(alloc, docs, attrs);
+                    let def = self.parse_def(alloc, attrs)?;
+                    self.private.insert(def.id.clone());
+                    return Ok(Some(Item::Def(def)));
+                }
+                _ => break,
+            }
+        }
+        let attrs = with_docs(alloc, docs, attrs);
+        self.parse_def(alloc, attrs).map(|def| Some(Item::Def(def)))
     }
-    pub fn parse_def(&mut self, alloc: &mut Alloc) -> Result<Option<Def>, Error> {
-        if self.sc.peek(alloc)?.ty() == TokenTy::Eof {
-            return Ok(None);
+
+    // the attributes apply to the rules of the definition itself, not to
+    // those lifted out of it
+    fn parse_def(&mut self, alloc: &mut Alloc, attrs: Vec<Attr>) -> Result<Def, Error> {
+        self.seen_def = true;
+        let mut rules = self.parse_rules(alloc, &[])?;
+        let id = rules[0].id.clone();
+        for rule in rules.iter_mut().filter(|rule| rule.id == id) {
+            rule.attrs.clone_from(&attrs);
         }
+        self.pending.extend(rules);
+        Ok(self.pending.pop_front().unwrap())
+    }
+
+    // `@name` followed by string and numeric literals
+    fn parse_attr(&mut self, alloc: &mut Alloc) -> Result<Attr, Error> {
+        let (loc, id) = self.sc.expect_identifier(alloc)?;
+        let mut args = Vec::new();
+        loop {
+            match self.sc.peek(alloc)?.ty() {
+                TokenTy::Str(s) => args.push(AttrArg::Str(s)),
+                TokenTy::Num(digits) => match digits.parse() {
+                    Ok(n) => args.push(AttrArg::Num(n)),
+                    Err(_) => {
+                        return Err(Error {
+                            loc: self.sc.peek(alloc)?.loc(),
+                            ty: ErrorTy::SyntaxError,
+                            desc: format!(
+                                "attribute argument `#{digits}` is not a natural number below 2^64"
+                            ),
+                        })
+                    }
+                },
+                _ => break,
+            }
+            self.sc.next_token(alloc)?;
+        }
+        let name = alloc.get_string(&id)[1..].to_string();
+        match ATTRS.iter().find(|(known, _)| *known == name) {
+            None => alloc.warn(loc, format!("unknown attribute `@{name}`")),
+            Some((_, arity)) if !arity.contains(&args.len()) => {
+                return Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: format!("wrong number of arguments for attribute `@{name}`"),
+                });
+            }
+            Some(_) => {}
+        }
+        let name = alloc.alloc_or_get(&name);
+        Ok(Attr { name, loc, args })
+    }
+
+    // the rules of one definition, its own first, then those lifted out of
+    // its `where` block and `let`s. `outer` are the variables in scope
+    // around it, for definitions in a `where` block
+    fn parse_rules(&mut self, alloc: &mut Alloc, outer: &[Id]) -> Result<Vec<Def>, Error> {
         let (id, loc, pat) = self.parse_expr(alloc, false)?;
-        if let Expr::Var { .. } = pat {
+        if matches!(pat, Expr::Var { .. }) || pattern::is_as(alloc, &pat) {
             return Err(Error {
                 loc,
                 ty: ErrorTy::SyntaxError,
@@ -135,41 +452,590 @@ impl<'a> Parser<'a> {
             });
         }
         self.sc.expect_token(alloc, TokenTy::Equal)?;
-        let (_, _, rep) = self.parse_expr(alloc, false)?;
+
+        // anonymous functions in the replacement and guards see the
+        // variables of the pattern, and are lifted like `where` functions
+        let mut scope = outer.to_vec();
+        lift::extend_scope(&mut scope, &pat);
+        let outer_lambdas = std::mem::take(&mut self.lambdas);
+        let outer_scope = std::mem::replace(&mut self.scope, scope.clone());
+        let outer_owner = std::mem::replace(&mut self.owner, id.clone());
+        let (_, _, mut rep) = self.parse_expr(alloc, false)?;
+        let mut guards = Vec::new();
+        while self.is_keyword(alloc, "if")? {
+            let (_, _, guard) = self.parse_expr(alloc, false)?;
+            self.sc.expect_token(alloc, TokenTy::Arrow)?;
+            let (_, _, nf) = self.parse_expr(alloc, false)?;
+            guards.push((guard, nf));
+        }
+        let mut lifted = std::mem::replace(&mut self.lambdas, outer_lambdas);
+        self.scope = outer_scope;
+        self.owner = outer_owner;
+
+        for (_, nf) in &guards {
+            lift::extend_scope(&mut scope, nf);
+        }
+        if self.is_keyword(alloc, "where")? {
+            self.sc.expect_token(alloc, TokenTy::Lbrace)?;
+            let mut declared = HashSet::new();
+            let mut locals = std::mem::take(&mut lifted);
+            while !self.sc.is_token(alloc, TokenTy::Rbrace)? {
+                let rules = self.parse_rules(alloc, &scope)?;
+                declared.insert(rules[0].id.clone());
+                locals.extend(rules);
+            }
+            let (defs, rewrite) = lift::lift_locals(alloc, &id, &scope, &declared, locals);
+            rep = rewrite(&rep);
+            for (guard, _) in &mut guards {
+                *guard = rewrite(guard);
+            }
+            lifted = defs;
+        }
         self.sc.expect_token(alloc, TokenTy::Semi)?;
+        self.rules(alloc, (id, loc, pat), rep, guards, &scope, lifted)
+    }
+
+    // desugars the patterns of a rule and lifts the `let`s out of it, giving
+    // one rule per alternative of an or-pattern, then `lifted`
+    fn rules(
+        &mut self,
+        alloc: &mut Alloc,
+        (id, loc, pat): (Id, Loc, Expr),
+        rep: Expr,
+        mut guards: Vec<(Expr, Expr)>,
+        scope: &[Id],
+        mut lifted: Vec<Def>,
+    ) -> Result<Vec<Def>, Error> {
+        let rep = lift::lift_lets(alloc, &id, scope, &rep, &mut lifted)?;
+        pattern::check_no_patterns(alloc, &rep)?;
+        for (guard, _) in &mut guards {
+            *guard = lift::lift_lets(alloc, &id, scope, guard, &mut lifted)?;
+            pattern::check_no_patterns(alloc, guard)?;
+        }
 
-        Ok(Some(Def { id, loc, pat, rep }))
+        let mut rules = Vec::new();
+        for pat in pattern::expand_or(alloc, &pat) {
+            let mut wildcards = 0;
+            let mut all_guards = Vec::new();
+            let pat = pattern::desugar(alloc, &pat, &mut all_guards, &mut wildcards)?;
+            for (guard, nf) in &guards {
+                let mut as_guards = Vec::new();
+                let nf = pattern::desugar(alloc, nf, &mut as_guards, &mut wildcards)?;
+                all_guards.push((guard.clone(), nf));
+                all_guards.extend(as_guards);
+            }
+            rules.push(Def {
+                id: id.clone(),
+                loc,
+                pat,
+                rep: rep.clone(),
+                guards: all_guards,
+                attrs: Vec::new(),
+            });
+        }
+        rules.extend(lifted);
+        Ok(rules)
     }
 
-    pub fn parse_expr(
+    // `#digits` spelled as the numerals pragma says. only native numerals
+    // reach past 2^64 or below zero
+    fn numeral(&self, digits: &str, loc: Loc) -> Result<Expr, Error> {
+        let n = Int::parse(digits).unwrap();
+        match (&self.numerals, n.to_u64()) {
+            (Numerals::Native, _) => Ok(Expr::Int { n: Rc::new(n), loc }),
+            (numerals, Some(n)) => Ok(numerals.encode(n, loc)),
+            (_, None) => Err(Error {
+                loc,
+                ty: ErrorTy::SyntaxError,
+                desc: format!(
+                    "`#{digits}` is not a natural number below 2^64, which needs `pragma numerals native;`"
+                ),
+            }),
+        }
+    }
+
+    fn parse_pragma(&mut self, alloc: &mut Alloc) -> Result<(), Error> {
+        self.sc.expect_identifier(alloc)?;
+        let (loc, name) = self.sc.expect_identifier(alloc)?;
+        let mut args = Vec::new();
+        while !self.sc.is_token(alloc, TokenTy::Semi)? {
+            args.push(self.sc.expect_identifier(alloc)?.1);
+        }
+        match (alloc.get_string(&name), &args[..]) {
+            ("list", [cons, nil]) => {
+                self.cons = cons.clone();
+                self.nil = nil.clone();
+                return Ok(());
+            }
+            ("numerals", [kind, names @ ..]) => {
+                let numerals = match (alloc.get_string(kind), names) {
+                    ("unary", []) => Some(Numerals::unary(alloc)),
+                    ("binary", []) => Some(Numerals::binary(alloc)),
+                    ("native", []) => Some(Numerals::Native),
+                    ("unary", [succ, zero]) => Some(Numerals::Unary {
+                        succ: succ.clone(),
+                        zero: zero.clone(),
+                    }),
+                    ("binary", [zero, even, odd]) => Some(Numerals::Binary {
+                        zero: zero.clone(),
+                        even: even.clone(),
+                        odd: odd.clone(),
+                    }),
+                    _ => None,
+                };
+                if let Some(numerals) = numerals {
+                    self.numerals = numerals;
+                    return Ok(());
+                }
+            }
+            _ => {}
+        }
+        let args: Vec<_> = args.iter().map(|a| alloc.get_string(a)).collect();
+
+        match (alloc.get_string(&name), &args[..]) {
+            ("dispatch", ["specific"]) => self.specific = true,
+            ("dispatch", ["ordered"]) => self.specific = false,
+            (name, _) => {
+                let text: Vec<_> = std::iter::once(name).chain(args).collect();
+                return Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: format!("unknown pragma `{}`", text.join(" ")),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // the end of an expression parsed on its own, outside any definition
+    // its anonymous functions could be lifted next to
+    pub(crate) fn expect_end(&mut self, alloc: &mut Alloc) -> Result<(), Error> {
+        if let Some(def) = self.lambdas.first() {
+            return Err(Error {
+                loc: def.loc,
+                ty: ErrorTy::SyntaxError,
+                desc: "anonymous functions are only allowed in definitions".into(),
+            });
+        }
+        self.sc.expect_token(alloc, TokenTy::Eof).map(|_| ())
+    }
+
+    // `\x y -> body`, after the first token, as an anonymous block `{ (_ x y) = body; }`
+    fn parse_lambda(
         &mut self,
         alloc: &mut Alloc,
-        is_func: bool,
+        first: &Id,
+        loc: Loc,
     ) -> Result<(Id, Loc, Expr), Error> {
-        if !self.sc.is_token(alloc, TokenTy::Lparen)? {
-            let (loc, id) = self.sc.expect_identifier(alloc)?;
-            return Ok((
-                id.clone(),
+        let underscore = alloc.alloc_or_get("_");
+        let mut pat = Expr::Fun {
+            id: underscore.clone(),
+            loc,
+        };
+        let name = alloc.get_string(first)[1..].to_string();
+        if !name.is_empty() {
+            let id = alloc.alloc_or_get(&name);
+            pat = Expr::app(pat, Expr::Var { id, loc }, loc);
+        }
+        while !self.is_keyword(alloc, "->")? {
+            let (_, _, param) = self.parse_expr(alloc, false)?;
+            pat = Expr::app(pat, param, loc);
+        }
+        if pat.as_app().is_none() {
+            return Err(Error {
                 loc,
-                if is_func {
-                    Expr::Fun { id, loc }
-                } else {
-                    Expr::Var { id, loc }
-                },
-            ));
+                ty: ErrorTy::SyntaxError,
+                desc: "an anonymous function needs at least one parameter".into(),
+            });
         }
-        let (id, loc, mut res) = self.parse_expr(alloc, true)?;
 
-        while !self.sc.is_token(alloc, TokenTy::Rparen)? {
-            let (_, _, arg) = self.parse_expr(alloc, false)?;
-            res = Expr::App(Box::new(App {
-                id: id.clone(),
-                f: res,
+        let mut scope = self.scope.clone();
+        lift::extend_scope(&mut scope, &pat);
+        let outer = std::mem::replace(&mut self.scope, scope.clone());
+        let body = self.parse_expr(alloc, false);
+        self.scope = outer;
+        let rules = self.rules(
+            alloc,
+            (underscore, loc, pat),
+            body?.2,
+            Vec::new(),
+            &scope,
+            Vec::new(),
+        )?;
+        Ok(self.lift_lambda(alloc, rules, loc))
+    }
+
+    // `{ (_ p) = e; ... }`, after the `{`, where `_` names the block itself
+    fn parse_block(&mut self, alloc: &mut Alloc, loc: Loc) -> Result<(Id, Loc, Expr), Error> {
+        let underscore = alloc.alloc_or_get("_");
+        let scope = self.scope.clone();
+        let mut rules = Vec::new();
+        while !self.sc.is_token(alloc, TokenTy::Rbrace)? {
+            let def = self.parse_rules(alloc, &scope)?;
+            if def[0].id != underscore {
+                return Err(Error {
+                    loc: def[0].loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: "rules of an anonymous block must be named `_`".into(),
+                });
+            }
+            rules.extend(def);
+        }
+        if rules.is_empty() {
+            return Err(Error {
                 loc,
-                arg,
-            }))
+                ty: ErrorTy::SyntaxError,
+                desc: "an anonymous block needs at least one rule".into(),
+            });
         }
+        Ok(self.lift_lambda(alloc, rules, loc))
+    }
+
+    // stashes the rules of an anonymous function for the enclosing definition,
+    // giving the lifted function applied to what it captured
+    fn lift_lambda(&mut self, alloc: &mut Alloc, rules: Vec<Def>, loc: Loc) -> (Id, Loc, Expr) {
+        let underscore = alloc.alloc_or_get("_");
+        let declared = HashSet::from([underscore.clone()]);
+        let (defs, rewrite) = lift::lift_locals(alloc, &self.owner, &self.scope, &declared, rules);
+        self.lambdas.extend(defs);
+        let e = rewrite(&Expr::Fun {
+            id: underscore,
+            loc,
+        });
+        (head_id(&e), loc, e)
+    }
 
-        Ok((id, loc, res))
+    // `macro (name args...) => template;`, expanded wherever a later
+    // application of `name` is parsed in this file. files that import this
+    // one do not see it
+    fn parse_macro(&mut self, alloc: &mut Alloc) -> Result<(), Error> {
+        self.sc.expect_identifier(alloc)?;
+        // the pattern names the macro rather than using it, and uses in the
+        // template are expanded along with it, once its variables are known
+        let macros = std::mem::take(&mut self.macros);
+        let pat = self.parse_expr(alloc, false);
+        let template = pat.and_then(|pat| {
+            self.sc.expect_token(alloc, TokenTy::Arrow)?;
+            Ok((pat, self.parse_expr(alloc, false)?.2))
+        });
+        self.macros = macros;
+        let ((id, loc, pat), template) = template?;
+        if matches!(pat, Expr::Var { .. } | Expr::Int { .. }) || head_id(&pat) != id {
+            return Err(Error {
+                loc,
+                ty: ErrorTy::SyntaxError,
+                desc: "a macro must be named by the function symbol it is applied to".into(),
+            });
+        }
+        self.sc.expect_token(alloc, TokenTy::Semi)?;
+        if let Some(def) = self.lambdas.first() {
+            return Err(Error {
+                loc: def.loc,
+                ty: ErrorTy::SyntaxError,
+                desc: "anonymous functions are not allowed in macros".into(),
+            });
+        }
+        self.macros.entry(id).or_default().push((pat, template));
+        Ok(())
+    }
+
+    // `infixl 6 + -;`, after which `(a + b - c)` reads `(- (+ a b) c)`
+    fn parse_fixity(&mut self, alloc: &mut Alloc) -> Result<(), Error> {
+        let (_, kw) = self.sc.expect_identifier(alloc)?;
+        let assoc = match alloc.get_string(&kw) {
+            "infixl" => Assoc::Left,
+            "infixr" => Assoc::Right,
+            _ => Assoc::None,
+        };
+        let (loc, prec) = self.sc.expect_identifier(alloc)?;
+        let prec = match alloc.get_string(&prec).parse() {
+            Ok(prec) if prec <= 9 => prec,
+            _ => {
+                return Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: format!(
+                        "expected a precedence from 0 to 9, found `{}`",
+                        alloc.get_string(&prec)
+                    ),
+                })
+            }
+        };
+        loop {
+            let (loc, op) = self.sc.expect_identifier(alloc)?;
+            let fixity = Fixity { assoc, prec };
+            if *self.fixities.entry(op.clone()).or_insert(fixity) != fixity {
+                return Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: format!("conflicting fixity for `{}`", alloc.get_string(&op)),
+                });
+            }
+            alloc.declare_fixity(op, fixity);
+            if self.sc.is_token(alloc, TokenTy::Semi)? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn is_keyword(&mut self, alloc: &mut Alloc, kw: &str) -> Result<bool, Error> {
+        let kw = TokenTy::Ident(alloc.alloc_or_get(kw));
+        self.sc.is_token(alloc, kw)
+    }
+
+    // `[a b c]` or `[a b | rest]`, after the `[`
+    fn parse_list(&mut self, alloc: &mut Alloc, loc: Loc) -> Result<(Id, Loc, Expr), Error> {
+        let mut items = Vec::new();
+        let mut tail = Expr::Fun {
+            id: self.nil.clone(),
+            loc,
+        };
+        while !self.sc.is_token(alloc, TokenTy::Rbracket)? {
+            if self.is_keyword(alloc, "|")? {
+                tail = self.parse_expr(alloc, false)?.2;
+                self.sc.expect_token(alloc, TokenTy::Rbracket)?;
+                break;
+            }
+            items.push(self.parse_expr(alloc, false)?.2);
+        }
+        Ok(self.list(items, tail, loc))
+    }
+
+    fn list(&self, items: Vec<Expr>, tail: Expr, loc: Loc) -> (Id, Loc, Expr) {
+        let e = items.into_iter().rev().fold(tail, |rest, item| {
+            let cons = Expr::Fun {
+                id: self.cons.clone(),
+                loc,
+            };
+            Expr::app(Expr::app(cons, item, loc), rest, loc)
+        });
+        (head_id(&e), loc, e)
+    }
+
+    pub fn parse_expr(
+        &mut self,
+        alloc: &mut Alloc,
+        is_func: bool,
+    ) -> Result<(Id, Loc, Expr), Error> {
+        crate::with_stacker(|| {
+            // anonymous functions may also be applied directly
+            let tok = self.sc.peek(alloc)?;
+            let loc = tok.loc();
+            match tok.ty() {
+                TokenTy::Ident(kw) if alloc.get_string(&kw).starts_with('\\') => {
+                    self.sc.next_token(alloc)?;
+                    return self.parse_lambda(alloc, &kw, loc);
+                }
+                TokenTy::Lbrace => {
+                    self.sc.next_token(alloc)?;
+                    return self.parse_block(alloc, loc);
+                }
+                _ => {}
+            }
+            if !is_func {
+                let tok = self.sc.peek(alloc)?;
+                let loc = tok.loc();
+                match tok.ty() {
+                    TokenTy::Str(text) => {
+                        self.sc.next_token(alloc)?;
+                        let items = text.bytes().map(|b| literal::byte(alloc, b, loc)).collect();
+                        let nil = Expr::Fun {
+                            id: self.nil.clone(),
+                            loc,
+                        };
+                        return Ok(self.list(items, nil, loc));
+                    }
+                    TokenTy::Lbracket => {
+                        self.sc.next_token(alloc)?;
+                        return self.parse_list(alloc, loc);
+                    }
+                    TokenTy::Char(b) => {
+                        self.sc.next_token(alloc)?;
+                        return Ok((BYTE, loc, literal::byte(alloc, b, loc)));
+                    }
+                    TokenTy::Ident(kw) if alloc.get_string(&kw) == "let" => {
+                        self.sc.next_token(alloc)?;
+                        let (_, _, p) = self.parse_expr(alloc, false)?;
+                        self.sc.expect_token(alloc, TokenTy::Equal)?;
+                        let (_, _, e) = self.parse_expr(alloc, false)?;
+                        if !self.is_keyword(alloc, "in")? {
+                            return Err(Error {
+                                loc: self.sc.peek(alloc)?.loc(),
+                                ty: ErrorTy::SyntaxError,
+                                desc: "expected `in` after the bound expression of a `let`".into(),
+                            });
+                        }
+                        let outer = self.scope.clone();
+                        lift::extend_scope(&mut self.scope, &p);
+                        let body = self.parse_expr(alloc, false);
+                        self.scope = outer;
+                        let e = lift::let_marker(alloc, p, e, body?.2, loc);
+                        return Ok((head_id(&e), loc, e));
+                    }
+                    TokenTy::Num(digits) => {
+                        self.sc.next_token(alloc)?;
+                        let e = self.numeral(&digits, loc)?;
+                        return Ok((head_id(&e), loc, e));
+                    }
+                    _ => {}
+                }
+            }
+            if !self.sc.is_token(alloc, TokenTy::Lparen)? {
+                let (loc, id) = self.sc.expect_identifier(alloc)?;
+                let name = alloc.get_string(&id).to_string();
+                if !is_func && name.len() > 1 && name.ends_with('@') {
+                    let var = alloc.alloc_or_get(&name[..name.len() - 1]);
+                    let (_, _, pat) = self.parse_expr(alloc, false)?;
+                    let var_expr = Expr::Var {
+                        id: var.clone(),
+                        loc,
+                    };
+                    return Ok((var, loc, pattern::marker(alloc, pattern::AS, var_expr, pat)));
+                }
+                return Ok((
+                    id.clone(),
+                    loc,
+                    if is_func {
+                        Expr::Fun { id, loc }
+                    } else {
+                        Expr::Var { id, loc }
+                    },
+                ));
+            }
+            let bare = matches!(self.sc.peek(alloc)?.ty(), TokenTy::Ident(_));
+            // a literal can only start a group as the first operand of an operator
+            let literal = matches!(
+                self.sc.peek(alloc)?.ty(),
+                TokenTy::Str(_) | TokenTy::Char(_) | TokenTy::Num(_) | TokenTy::Lbracket
+            );
+            let (id, loc, head) = self.parse_expr(alloc, !literal)?;
+
+            // the applications between declared operators, each a head and its
+            // arguments; without operators this is the whole group
+            let mut segments = vec![(loc, vec![head])];
+            let mut ops = Vec::new();
+            while !self.sc.is_token(alloc, TokenTy::Rparen)? {
+                let tok = self.sc.peek(alloc)?;
+                let op_loc = tok.loc();
+                if let TokenTy::Ident(op) = tok.ty() {
+                    if let Some(&fixity) = self.fixities.get(&op) {
+                        self.sc.next_token(alloc)?;
+                        ops.push((op, op_loc, fixity));
+                        segments.push((op_loc, Vec::new()));
+                        continue;
+                    }
+                }
+                let (_, _, mut arg) = self.parse_expr(alloc, false)?;
+                while self.is_keyword(alloc, "|")? {
+                    let (_, _, alt) = self.parse_expr(alloc, false)?;
+                    arg = pattern::marker(alloc, pattern::OR, arg, alt);
+                }
+                segments.last_mut().unwrap().1.push(arg);
+            }
+
+            if literal && segments[0].1.len() > 1 {
+                return Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: "a literal cannot be applied to arguments".into(),
+                });
+            }
+            if ops.is_empty() {
+                let (_, args) = segments.pop().unwrap();
+                let mut args = args.into_iter();
+                let res = args.next().unwrap();
+                let res = args.fold(res, |res, arg| {
+                    Expr::App(Box::new(App {
+                        id: id.clone(),
+                        f: res,
+                        loc,
+                        arg,
+                    }))
+                });
+                return self.expand(alloc, (id, loc, res));
+            }
+
+            let mut operands = Vec::new();
+            for (i, (seg_loc, seg)) in segments.into_iter().enumerate() {
+                let mut seg = seg.into_iter();
+                let Some(head) = seg.next() else {
+                    return Err(Error {
+                        loc: seg_loc,
+                        ty: ErrorTy::SyntaxError,
+                        desc: "expected an operand".into(),
+                    });
+                };
+                let first = seg.next();
+                operands.push(match (head, first) {
+                    // a lone identifier between operators is a variable
+                    (Expr::Fun { id, loc }, None) if i == 0 && bare => Expr::Var { id, loc },
+                    (head, None) => head,
+                    // and one followed by arguments is the function applied
+                    (head, Some(first)) => {
+                        let head = match head {
+                            Expr::Var { id, loc } => Expr::Fun { id, loc },
+                            head => head,
+                        };
+                        let res = Expr::app(head, first, seg_loc);
+                        seg.fold(res, |res, arg| Expr::app(res, arg, seg_loc))
+                    }
+                });
+            }
+            let res = infix(alloc, operands, ops)?;
+            self.expand(alloc, (head_id(&res), loc, res))
+        })
+    }
+
+    fn expand(&mut self, alloc: &mut Alloc, e: (Id, Loc, Expr)) -> Result<(Id, Loc, Expr), Error> {
+        if !self.macros.contains_key(&e.0) {
+            return Ok(e);
+        }
+        let res = macros::expand(alloc, &self.macros, &e.2)?;
+        Ok((head_id(&res), e.1, res))
+    }
+}
+
+// resolves `a op b op c ...` by precedence, from the operands and operators
+// in source order
+fn infix(alloc: &Alloc, operands: Vec<Expr>, ops: Vec<(Id, Loc, Fixity)>) -> Result<Expr, Error> {
+    fn reduce(out: &mut Vec<Expr>, (op, loc, _): (Id, Loc, Fixity)) {
+        let b = out.pop().unwrap();
+        let a = out.pop().unwrap();
+        let f = Expr::app(Expr::Fun { id: op, loc }, a, loc);
+        out.push(Expr::app(f, b, loc));
+    }
+
+    let mut operands = operands.into_iter();
+    let mut out = vec![operands.next().unwrap()];
+    let mut stack: Vec<(Id, Loc, Fixity)> = Vec::new();
+    for (op, arg) in ops.into_iter().zip(operands) {
+        while let Some(top) = stack.last() {
+            let (prev, next) = (top.2, op.2);
+            if prev.prec < next.prec
+                || (prev.prec == next.prec
+                    && prev.assoc == Assoc::Right
+                    && next.assoc == Assoc::Right)
+            {
+                break;
+            }
+            if prev.prec == next.prec && (prev.assoc != next.assoc || prev.assoc == Assoc::None) {
+                return Err(Error {
+                    loc: op.1,
+                    ty: ErrorTy::SyntaxError,
+                    desc: format!(
+                        "cannot chain `{}` and `{}` without parentheses",
+                        alloc.get_string(&top.0),
+                        alloc.get_string(&op.0)
+                    ),
+                });
+            }
+            reduce(&mut out, stack.pop().unwrap());
+        }
+        stack.push(op);
+        out.push(arg);
+    }
+    while let Some(op) = stack.pop() {
+        reduce(&mut out, op);
     }
+    Ok(out.pop().unwrap())
 }
@@ -0,0 +1,132 @@
+use crate::{
+    alloc::{Alloc, Id},
+    error::{Error, ErrorTy},
+    parser::{App, Expr},
+};
+
+// while parsing, `v@p` and `p | q` are kept as applications of these
+// symbols, which contain a `;` and so cannot clash with source identifiers
+pub(crate) const AS: &str = "@;";
+pub(crate) const OR: &str = "|;";
+
+pub(crate) fn marker(alloc: &mut Alloc, name: &str, a: Expr, b: Expr) -> Expr {
+    let id = alloc.alloc_or_get(name);
+    let loc = a.loc();
+    let f = Expr::App(Box::new(App {
+        id: id.clone(),
+        loc,
+        f: Expr::Fun {
+            id: id.clone(),
+            loc,
+        },
+        arg: a,
+    }));
+    Expr::App(Box::new(App { id, loc, f, arg: b }))
+}
+
+fn split<'a>(alloc: &mut Alloc, name: &str, e: &'a Expr) -> Option<(&'a Expr, &'a Expr)> {
+    let id = alloc.alloc_or_get(name);
+    let outer = e.as_app()?;
+    let inner = outer.f.as_app()?;
+    match &inner.f {
+        Expr::Fun { id: id2, .. } if *id2 == id => Some((&inner.arg, &outer.arg)),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_as(alloc: &mut Alloc, e: &Expr) -> bool {
+    split(alloc, AS, e).is_some()
+}
+
+pub(crate) fn check_no_patterns(alloc: &mut Alloc, e: &Expr) -> Result<(), Error> {
+    let desc = if split(alloc, AS, e).is_some() {
+        "as-patterns are only allowed in patterns"
+    } else if split(alloc, OR, e).is_some() {
+        "or-patterns are only allowed in patterns"
+    } else {
+        return match e.as_app() {
+            Some(f) => {
+                check_no_patterns(alloc, &f.f)?;
+                check_no_patterns(alloc, &f.arg)
+            }
+            None => Ok(()),
+        };
+    };
+    Err(Error {
+        loc: e.loc(),
+        ty: ErrorTy::SyntaxError,
+        desc: desc.into(),
+    })
+}
+
+// every alternative of a pattern containing or-patterns, in source order
+pub(crate) fn expand_or(alloc: &mut Alloc, e: &Expr) -> Vec<Expr> {
+    if let Some((a, b)) = split(alloc, OR, e) {
+        let mut res = expand_or(alloc, a);
+        res.extend(expand_or(alloc, b));
+        return res;
+    }
+    let Some(app) = e.as_app() else {
+        return vec![e.clone()];
+    };
+    let args = expand_or(alloc, &app.arg);
+    let mut res = Vec::new();
+    for f in expand_or(alloc, &app.f) {
+        for arg in &args {
+            res.push(Expr::App(Box::new(App {
+                id: app.id.clone(),
+                loc: app.loc,
+                f: f.clone(),
+                arg: arg.clone(),
+            })));
+        }
+    }
+    res
+}
+
+// gives every `_` its own variable, and turns `v@p` into the variable `v`
+// plus a guard `v => p`, so the matched term is bound rather than rebuilt
+pub(crate) fn desugar(
+    alloc: &mut Alloc,
+    e: &Expr,
+    guards: &mut Vec<(Expr, Expr)>,
+    wildcards: &mut usize,
+) -> Result<Expr, Error> {
+    if let Some((v, p)) = split(alloc, AS, e) {
+        let (v, p) = (v.clone(), p.clone());
+        let i = guards.len();
+        guards.push((v.clone(), Expr::default()));
+        guards[i].1 = desugar(alloc, &p, guards, wildcards)?;
+        return Ok(v);
+    }
+    if let Some((a, _)) = split(alloc, OR, e) {
+        return Err(Error {
+            loc: a.loc(),
+            ty: ErrorTy::SyntaxError,
+            desc: "or-patterns are not allowed here".into(),
+        });
+    }
+    Ok(match e {
+        Expr::Var { id, loc } if alloc.get_string(id) == "_" => {
+            *wildcards += 1;
+            Expr::Var {
+                id: wildcard(alloc, *wildcards),
+                loc: *loc,
+            }
+        }
+        Expr::Var { .. } | Expr::Fun { .. } | Expr::Int { .. } => e.clone(),
+        _ => {
+            let app = e.as_app().unwrap();
+            Expr::App(Box::new(App {
+                id: app.id.clone(),
+                loc: app.loc,
+                f: desugar(alloc, &app.f, guards, wildcards)?,
+                arg: desugar(alloc, &app.arg, guards, wildcards)?,
+            }))
+        }
+    })
+}
+
+fn wildcard(alloc: &mut Alloc, n: usize) -> Id {
+    alloc.alloc_or_get(&format!("_;{n}"))
+}
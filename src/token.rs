@@ -1,32 +1,64 @@
+use std::{collections::VecDeque, ops::Range};
+
 use crate::{
     alloc::{Alloc, DisplayWithAlloc, Id},
     error::{Error, ErrorTy, Loc},
+    int::Int,
 };
 
 pub struct Scanner<'a> {
     loc: Loc,
     peeked: Option<Result<Token, Error>>,
+    src: &'a str,
     rest: &'a str,
+    // tokens scanned before, for a scanner over a `Cst`
+    replay: Option<VecDeque<Token>>,
+}
+
+struct Scanned {
+    ty: TokenTy,
+    loc: Loc,
 }
 
 #[derive(Clone, Debug)]
 pub struct Token {
     ty: TokenTy,
     loc: Loc,
+    // the whitespace and comments before the token, and the token itself,
+    // as byte ranges of the source
+    pub(crate) trivia: Range<usize>,
+    pub(crate) text: Range<usize>,
+    // the `##` comments among the trivia
+    docs: Vec<(Loc, String)>,
 }
 
 impl Token {
     pub fn ty(self) -> TokenTy {
         self.ty
     }
+    pub fn loc(&self) -> Loc {
+        self.loc
+    }
+    pub(crate) fn kind(&self) -> &TokenTy {
+        &self.ty
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TokenTy {
     Ident(Id),
+    Str(String),
+    Char(u8),
+    // the digits of `#n`, with a leading `-` if negative
+    Num(String),
     Lparen,
     Rparen,
+    Lbracket,
+    Rbracket,
+    Lbrace,
+    Rbrace,
     Equal,
+    Arrow,
     Semi,
     Eof,
 }
@@ -42,10 +74,29 @@ impl DisplayWithAlloc for TokenTy {
                 s.push('`');
                 return;
             }
+            Str(x) => {
+                s.push_str("string literal ");
+                s.push_str(&format!("{x:?}"));
+                return;
+            }
+            Char(b) => {
+                s.push_str("character literal ");
+                s.push_str(&format!("{:?}", *b as char));
+                return;
+            }
+            Num(n) => {
+                s.push_str(&format!("numeric literal `#{n}`"));
+                return;
+            }
             x => match x {
                 Equal => "token `=`",
+                Arrow => "token `=>`",
                 Lparen => "token `(`",
                 Rparen => "token `)`",
+                Lbracket => "token `[`",
+                Rbracket => "token `]`",
+                Lbrace => "token `{`",
+                Rbrace => "token `}`",
                 Semi => "token `;`",
                 Eof => "EOF",
                 _ => unreachable!(),
@@ -60,10 +111,25 @@ impl<'a> Scanner<'a> {
         Self {
             loc: Loc::new(),
             peeked: None,
+            src: s,
             rest: s,
+            replay: None,
         }
     }
 
+    // a scanner giving `tokens` again, which must end with `Eof`
+    pub(crate) fn replay(tokens: Vec<Token>) -> Scanner<'static> {
+        Scanner {
+            replay: Some(tokens.into()),
+            ..Scanner::new("")
+        }
+    }
+
+    pub fn in_file(mut self, file: usize) -> Self {
+        self.loc.file = file;
+        self
+    }
+
     pub fn expect_identifier(&mut self, alloc: &mut Alloc) -> Result<(Loc, Id), Error> {
         let res = self.next_token(alloc)?;
         if let TokenTy::Ident(x) = res.ty {
@@ -77,6 +143,19 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    pub fn expect_string(&mut self, alloc: &mut Alloc) -> Result<(Loc, String), Error> {
+        let res = self.next_token(alloc)?;
+        if let TokenTy::Str(x) = res.ty {
+            Ok((res.loc, x))
+        } else {
+            Err(Error {
+                loc: res.loc,
+                ty: ErrorTy::SyntaxError,
+                desc: format!("expected string literal, found {}", res.ty.to_string(alloc)),
+            })
+        }
+    }
+
     pub fn expect_token(&mut self, alloc: &mut Alloc, token: TokenTy) -> Result<Token, Error> {
         let res = self.next_token(alloc)?;
         if res.ty != token {
@@ -95,7 +174,12 @@ impl<'a> Scanner<'a> {
     }
 
     pub fn loc(&self) -> Loc {
-        self.loc.clone()
+        self.loc
+    }
+
+    // the `##` comments before the next token, one per line
+    pub fn docs(&mut self, alloc: &mut Alloc) -> Result<Vec<(Loc, String)>, Error> {
+        Ok(self.peek(alloc)?.docs)
     }
 
     pub fn is_token(&mut self, alloc: &mut Alloc, tok: TokenTy) -> Result<bool, Error> {
@@ -120,10 +204,33 @@ impl<'a> Scanner<'a> {
     }
 
     fn next_token_internal(&mut self, alloc: &mut Alloc) -> Result<Token, Error> {
-        self.skip_whitespace();
+        if let Some(tokens) = &mut self.replay {
+            let tok = match tokens.len() {
+                1 => tokens.front().cloned(),
+                _ => tokens.pop_front(),
+            };
+            return Ok(tok.expect("a replayed scanner ends with `Eof`"));
+        }
+        let start = self.pos();
+        let docs = self.skip_whitespace()?;
+        let mid = self.pos();
+        let Scanned { ty, loc } = self.scan(alloc)?;
+        Ok(Token {
+            ty,
+            loc,
+            trivia: start..mid,
+            text: mid..self.pos(),
+            docs,
+        })
+    }
 
+    fn pos(&self) -> usize {
+        self.src.len() - self.rest.len()
+    }
+
+    fn scan(&mut self, alloc: &mut Alloc) -> Result<Scanned, Error> {
         if self.rest.is_empty() {
-            return Ok(Token {
+            return Ok(Scanned {
                 loc: self.loc(),
                 ty: TokenTy::Eof,
             });
@@ -131,16 +238,79 @@ impl<'a> Scanner<'a> {
         let mut iter = self.rest.char_indices();
         let (_, c) = iter.next().unwrap();
 
+        if c == '"' {
+            return self.quoted(c).map(|(loc, s)| Scanned {
+                loc,
+                ty: TokenTy::Str(s),
+            });
+        }
+
+        if c == '\'' {
+            let (loc, s) = self.quoted(c)?;
+            return match s.as_bytes() {
+                &[b] => Ok(Scanned {
+                    loc,
+                    ty: TokenTy::Char(b),
+                }),
+                _ => Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: "a character literal must be exactly one byte".into(),
+                }),
+            };
+        }
+
+        if c == '|' {
+            let loc = self.loc();
+            if let Some(id) = self.quoted_symbol(alloc)? {
+                return Ok(Scanned {
+                    loc,
+                    ty: TokenTy::Ident(id),
+                });
+            }
+        }
+
+        if c == '#' {
+            let loc = self.loc();
+            let end = self.rest.find(is_break).unwrap_or(self.rest.len());
+            let digits = self.rest[1..end].to_string();
+            if Int::parse(&digits).is_none() {
+                return Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: format!("invalid numeric literal `#{digits}`"),
+                });
+            }
+            self.skip(end);
+            return Ok(Scanned {
+                loc,
+                ty: TokenTy::Num(digits),
+            });
+        }
+
+        if c == '=' && self.rest[1..].starts_with('>') {
+            let ret = Ok(Scanned {
+                loc: self.loc(),
+                ty: TokenTy::Arrow,
+            });
+            self.skip(2);
+            return ret;
+        }
+
         if is_break(c) {
             use TokenTy::*;
 
-            let ret = Ok(Token {
+            let ret = Ok(Scanned {
                 loc: self.loc(),
                 ty: match c {
                     ';' => Semi,
                     '=' => Equal,
                     '(' => Lparen,
                     ')' => Rparen,
+                    '[' => Lbracket,
+                    ']' => Rbracket,
+                    '{' => Lbrace,
+                    '}' => Rbrace,
                     _ => {
                         return Err(Error {
                             loc: self.loc(),
@@ -160,13 +330,83 @@ impl<'a> Scanner<'a> {
                     break;
                 }
             }
-            Ok(Token {
+            Ok(Scanned {
                 loc: self.loc(),
                 ty: self.ident(i, alloc),
             })
         }
     }
 
+    // text up to the closing `quote`, with escapes resolved
+    fn quoted(&mut self, quote: char) -> Result<(Loc, String), Error> {
+        let loc = self.loc();
+        let err = |desc: String| Error {
+            loc,
+            ty: ErrorTy::SyntaxError,
+            desc,
+        };
+        let mut s = String::new();
+        let mut chars = self.rest.char_indices().skip(1);
+        let end = loop {
+            match chars.next() {
+                None => return Err(err(format!("unterminated literal, expected `{quote}`"))),
+                Some((i, c)) if c == quote => break i + 1,
+                Some((_, '\\')) => s.push(match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, 'r')) => '\r',
+                    Some((_, '0')) => '\0',
+                    Some((_, 'x')) => {
+                        // only ASCII, so that a string's bytes are its UTF-8 encoding
+                        let hex: String = chars.by_ref().take(2).map(|(_, c)| c).collect();
+                        u8::from_str_radix(&hex, 16)
+                            .ok()
+                            .filter(u8::is_ascii)
+                            .ok_or_else(|| err(format!("invalid escape `\\x{hex}`")))?
+                            as char
+                    }
+                    Some((_, c @ ('\\' | '"' | '\'' | '|'))) => c,
+                    Some((_, c)) => return Err(err(format!("invalid escape `\\{c}`"))),
+                    None => return Err(err(format!("unterminated literal, expected `{quote}`"))),
+                }),
+                Some((_, c)) => s.push(c),
+            }
+        };
+        self.skip(end);
+        Ok((loc, s))
+    }
+
+    // `|...|` with the same escapes as strings, for symbols containing
+    // whitespace or delimiters. a `|` followed by whitespace, or without a
+    // closing `|` before a delimiter on the same line, starts a plain symbol.
+    // names with a `;` or starting with `#` or `@` are kept for the symbols
+    // the interpreter makes up, so they cannot be quoted
+    fn quoted_symbol(&mut self, alloc: &mut Alloc) -> Result<Option<Id>, Error> {
+        if self.rest[1..].starts_with(|c: char| c.is_whitespace() || c == '|') {
+            return Ok(None);
+        }
+        let line = &self.rest[..self.rest.find('\n').unwrap_or(self.rest.len())];
+        let mut probe = Scanner::new(line);
+        let Ok((_, s)) = probe.quoted('|') else {
+            return Ok(None);
+        };
+        if probe.rest.starts_with(|c| !is_break(c)) {
+            return Ok(None);
+        }
+        if s.contains(';') || s.starts_with(['#', '@']) {
+            return Err(Error {
+                loc: self.loc(),
+                ty: ErrorTy::SyntaxError,
+                desc: format!(
+                    "`{}` is reserved: quoted symbols cannot contain `;` or start with `#` or `@`",
+                    crate::literal::escape(&s, '|')
+                ),
+            });
+        }
+        self.skip(line.len() - probe.rest.len());
+        Ok(Some(alloc.alloc_or_get(&s)))
+    }
+
     fn ident(&mut self, i: usize, alloc: &mut Alloc) -> TokenTy {
         use TokenTy::*;
         let id = alloc.alloc_or_get(&self.rest[..i]);
@@ -174,7 +414,9 @@ impl<'a> Scanner<'a> {
         Ident(id)
     }
 
-    fn skip_whitespace(&mut self) {
+    // skips whitespace and comments, returning the `##` ones
+    fn skip_whitespace(&mut self) -> Result<Vec<(Loc, String)>, Error> {
+        let mut docs = Vec::new();
         loop {
             let i = self
                 .rest
@@ -183,18 +425,60 @@ impl<'a> Scanner<'a> {
                 .map(|(i, _c)| i)
                 .unwrap_or(self.rest.len());
             self.skip(i);
-            if self.rest.chars().next() != Some('#') {
-                break;
+            if self.rest.starts_with("#|") {
+                self.block_comment()?;
+                continue;
+            }
+            // `#` followed by a digit, or by `-` and a digit, starts a numeric
+            // literal, not a comment
+            match self.rest.strip_prefix('#') {
+                Some(r)
+                    if !r
+                        .trim_start_matches('-')
+                        .starts_with(|c: char| c.is_ascii_digit()) => {}
+                _ => break Ok(docs),
             }
+            let loc = self.loc();
             let i = self
                 .rest
                 .char_indices()
                 .find(|(_i, c)| *c == '\n')
                 .map(|(i, _c)| i + 1)
                 .unwrap_or(self.rest.len());
+            if let Some(doc) = self.rest[..i].strip_prefix("##") {
+                let doc = doc.trim_end();
+                let doc = doc.strip_prefix(' ').unwrap_or(doc);
+                docs.push((loc, doc.to_string()));
+            }
             self.skip(i);
         }
     }
+
+    // `#| ... |#`, which may contain other block comments
+    fn block_comment(&mut self) -> Result<(), Error> {
+        let loc = self.loc();
+        let mut depth = 0;
+        loop {
+            if self.rest.starts_with("#|") {
+                depth += 1;
+                self.skip(2);
+            } else if self.rest.starts_with("|#") {
+                depth -= 1;
+                self.skip(2);
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else if let Some(c) = self.rest.chars().next() {
+                self.skip(c.len_utf8());
+            } else {
+                return Err(Error {
+                    loc,
+                    ty: ErrorTy::SyntaxError,
+                    desc: "unterminated block comment, expected `|#`".into(),
+                });
+            }
+        }
+    }
     fn skip(&mut self, len: usize) {
         for c in self.rest[..len].chars() {
             self.loc.col();
@@ -206,6 +490,6 @@ impl<'a> Scanner<'a> {
     }
 }
 
-fn is_break(c: char) -> bool {
-    "()=;".contains(c) || c.is_whitespace()
+pub(crate) fn is_break(c: char) -> bool {
+    "()[]{}=;".contains(c) || c.is_whitespace()
 }
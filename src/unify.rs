@@ -1,20 +1,74 @@
-use std::collections::HashMap;
+use std::{borrow::Borrow, collections::HashMap};
 
-use crate::{alloc::Id, App, Def, Expr};
+use crate::{
+    alloc::{Alloc, DisplayWithAlloc, Id, INT},
+    App, Attr, Def, Expr, Rules,
+};
 
 impl Def {
-    pub fn apply(&self, e: &mut Expr) -> bool {
+    pub fn apply(&self, defs: &Rules, e: &mut Expr, alloc: &mut Alloc) -> bool {
         let mut bindings = HashMap::new();
-        if !unify(&mut bindings, &self.pat, e) {
+        if !matches(&mut bindings, &self.pat, e) {
             return false;
         }
+        if self.guards.is_empty() {
+            let new_expr = substitute(&bindings, &self.rep);
+            self.trace(alloc, e, &new_expr);
+            *e = new_expr;
+            return true;
+        }
+
+        // guards are reduced left to right, each seeing the variables bound
+        // by the pattern and by the normal forms of the guards before it
+        let mut bindings: HashMap<_, Expr> =
+            bindings.into_iter().map(|(k, v)| (k, v.clone())).collect();
+        for (guard, nf) in &self.guards {
+            let mut res = substitute(&bindings, guard);
+            crate::apply(defs, &mut res, alloc);
+            let mut new = HashMap::new();
+            if !matches(&mut new, nf, &res) {
+                return false;
+            }
+            for (k, v) in new {
+                match bindings.get(k) {
+                    Some(old) if old != v => return false,
+                    Some(_) => {}
+                    None => {
+                        bindings.insert(k, v.clone());
+                    }
+                }
+            }
+        }
         let new_expr = substitute(&bindings, &self.rep);
+        self.trace(alloc, e, &new_expr);
         *e = new_expr;
         true
     }
+
+    // the first attribute of the definition called `name`
+    pub fn attr(&self, alloc: &Alloc, name: &str) -> Option<&Attr> {
+        self.attrs
+            .iter()
+            .find(|attr| alloc.get_string(&attr.name) == name)
+    }
+
+    fn trace(&self, alloc: &Alloc, old: &Expr, new: &Expr) {
+        if !self.attrs.is_empty() && self.attr(alloc, "trace").is_some() {
+            eprintln!(
+                "trace: {} => {}",
+                old.to_string(alloc),
+                new.to_string(alloc)
+            );
+        }
+    }
 }
 
-fn unify<'a>(b: &mut HashMap<&'a Id, &'a Expr>, pat: &'a Expr, e: &'a Expr) -> bool {
+// one-way matching: only variables of `pat` get bound
+pub(crate) fn matches<'p, 'e>(
+    b: &mut HashMap<&'p Id, &'e Expr>,
+    pat: &'p Expr,
+    e: &'e Expr,
+) -> bool {
     match (pat, e) {
         (Expr::Var { id, .. }, _) => {
             if let Some(e2) = b.get(&id) {
@@ -25,21 +79,22 @@ fn unify<'a>(b: &mut HashMap<&'a Id, &'a Expr>, pat: &'a Expr, e: &'a Expr) -> b
             }
         }
         (Expr::App(f1), Expr::App(f2)) if f1.id == f2.id => {
-            unify(b, &f1.f, &f2.f) && unify(b, &f1.arg, &f2.arg)
+            matches(b, &f1.f, &f2.f) && matches(b, &f1.arg, &f2.arg)
         }
         (Expr::App(f1), Expr::RedApp(f2)) if f1.id == f2.id => {
-            unify(b, &f1.f, &f2.f) && unify(b, &f1.arg, &f2.arg)
+            matches(b, &f1.f, &f2.f) && matches(b, &f1.arg, &f2.arg)
         }
 
         (Expr::Fun { id, .. }, Expr::Fun { id: id2, .. }) => id == id2,
+        (Expr::Int { n, .. }, Expr::Int { n: n2, .. }) => n == n2,
         _ => false,
     }
 }
 
-fn substitute(b: &HashMap<&Id, &Expr>, rep: &Expr) -> Expr {
+fn substitute<E: Borrow<Expr>>(b: &HashMap<&Id, E>, rep: &Expr) -> Expr {
     match rep {
-        Expr::Var { id, .. } => b[&id].clone(),
-        Expr::Fun { id, .. } if b.contains_key(&*id) => b[&id].clone(),
+        Expr::Var { id, .. } => b[&id].borrow().clone(),
+        Expr::Fun { id, .. } if b.contains_key(id) => b[&id].borrow().clone(),
         Expr::App(f) => {
             let res = substitute(b, &f.f);
             Expr::App(Box::new(App {
@@ -49,15 +104,193 @@ fn substitute(b: &HashMap<&Id, &Expr>, rep: &Expr) -> Expr {
                 arg: substitute(b, &f.arg),
             }))
         }
-        Expr::Fun { .. } => rep.clone(),
+        Expr::Fun { .. } | Expr::Int { .. } => rep.clone(),
         _ => unreachable!(),
     }
 }
 
+// replaces the variables in `map`, and the function symbols standing for them
+// in replacements, with the variables they are mapped to
+pub(crate) fn rename(map: &HashMap<Id, Id>, e: &Expr) -> Expr {
+    crate::with_stacker(|| match e {
+        Expr::Var { id, loc } | Expr::Fun { id, loc } if map.contains_key(id) => Expr::Var {
+            id: map[id].clone(),
+            loc: *loc,
+        },
+        Expr::Var { .. } | Expr::Fun { .. } | Expr::Int { .. } => e.clone(),
+        _ => {
+            let f = e.as_app().unwrap();
+            let new_f = rename(map, &f.f);
+            Expr::App(Box::new(App {
+                id: get_id(&new_f),
+                loc: f.loc,
+                f: new_f,
+                arg: rename(map, &f.arg),
+            }))
+        }
+    })
+}
+
+// whether `e` is an instance of `pat`, i.e. `pat` matches every term `e` does
+pub fn is_instance(e: &Expr, pat: &Expr) -> bool {
+    matches(&mut HashMap::new(), pat, e)
+}
+
 fn get_id(a: &Expr) -> Id {
     match a {
         Expr::Var { id, .. } | Expr::Fun { id, .. } => id.clone(),
+        Expr::Int { .. } => INT,
         Expr::App(f) => f.id.clone(),
         Expr::RedApp(f) => f.id.clone(),
     }
 }
+
+// idempotent substitution: no bound variable occurs in any binding
+#[derive(Clone, Debug, Default)]
+pub struct Subst(HashMap<Id, Expr>);
+
+impl Subst {
+    pub fn get(&self, id: &Id) -> Option<&Expr> {
+        self.0.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Id, &Expr)> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn apply(&self, e: &Expr) -> Expr {
+        crate::with_stacker(|| match e {
+            Expr::Var { id, .. } => self.0.get(id).cloned().unwrap_or_else(|| e.clone()),
+            Expr::Fun { .. } | Expr::Int { .. } => e.clone(),
+            Expr::App(_) | Expr::RedApp(_) => {
+                let f = e.as_app().unwrap();
+                let res = self.apply(&f.f);
+                Expr::App(Box::new(App {
+                    id: get_id(&res),
+                    f: res,
+                    loc: f.loc,
+                    arg: self.apply(&f.arg),
+                }))
+            }
+        })
+    }
+
+    fn bind(&mut self, id: Id, e: Expr) {
+        let single = Subst(HashMap::from([(id.clone(), e.clone())]));
+        for v in self.0.values_mut() {
+            *v = single.apply(v);
+        }
+        self.0.insert(id, e);
+    }
+}
+
+// most general unifier, treating variables on both sides as unknowns.
+// applying the result to `a` and `b` makes them equal
+pub fn unify(a: &Expr, b: &Expr) -> Option<Subst> {
+    let mut s = Subst::default();
+    mgu_internal(&mut s, a, b).then_some(s)
+}
+
+fn mgu_internal(s: &mut Subst, a: &Expr, b: &Expr) -> bool {
+    crate::with_stacker(|| match (a, b) {
+        (Expr::Var { id, .. }, _) if s.get(id).is_some() => {
+            let a = s.apply(a);
+            mgu_internal(s, &a, b)
+        }
+        (_, Expr::Var { id, .. }) if s.get(id).is_some() => {
+            let b = s.apply(b);
+            mgu_internal(s, a, &b)
+        }
+        (Expr::Var { id, .. }, _) | (_, Expr::Var { id, .. }) => {
+            let other = if matches!(a, Expr::Var { id: id2, .. } if id2 == id) {
+                s.apply(b)
+            } else {
+                s.apply(a)
+            };
+            match other {
+                Expr::Var { id: ref id2, .. } if id2 == id => true,
+                _ if occurs(id, &other) => false,
+                _ => {
+                    s.bind(id.clone(), other);
+                    true
+                }
+            }
+        }
+        (Expr::Fun { id, .. }, Expr::Fun { id: id2, .. }) => id == id2,
+        (Expr::Int { n, .. }, Expr::Int { n: n2, .. }) => n == n2,
+        _ => match (a.as_app(), b.as_app()) {
+            (Some(f1), Some(f2)) => {
+                mgu_internal(s, &f1.f, &f2.f) && mgu_internal(s, &f1.arg, &f2.arg)
+            }
+            _ => false,
+        },
+    })
+}
+
+fn occurs(v: &Id, e: &Expr) -> bool {
+    match e {
+        Expr::Var { id, .. } => id == v,
+        Expr::Fun { .. } | Expr::Int { .. } => false,
+        Expr::App(_) | Expr::RedApp(_) => {
+            let f = e.as_app().unwrap();
+            occurs(v, &f.f) || occurs(v, &f.arg)
+        }
+    }
+}
+
+// least general generalisation: a term `g` with `sa.apply(g) == a` and
+// `sb.apply(g) == b`, using one fresh variable per distinct pair of
+// disagreeing subterms
+pub fn generalize(alloc: &mut Alloc, a: &Expr, b: &Expr) -> (Expr, Subst, Subst) {
+    let mut pairs = Vec::new();
+    let g = lgg(alloc, &mut pairs, a, b);
+    let mut sa = Subst::default();
+    let mut sb = Subst::default();
+    for (id, x, y) in pairs {
+        sa.0.insert(id.clone(), x);
+        sb.0.insert(id, y);
+    }
+    (g, sa, sb)
+}
+
+fn lgg(alloc: &mut Alloc, pairs: &mut Vec<(Id, Expr, Expr)>, a: &Expr, b: &Expr) -> Expr {
+    crate::with_stacker(|| match (a, b) {
+        (Expr::Var { id, .. }, Expr::Var { id: id2, .. })
+        | (Expr::Fun { id, .. }, Expr::Fun { id: id2, .. })
+            if id == id2 =>
+        {
+            a.clone()
+        }
+        (Expr::Int { n, .. }, Expr::Int { n: n2, .. }) if n == n2 => a.clone(),
+        _ => match (a.as_app(), b.as_app()) {
+            (Some(f1), Some(f2)) => {
+                let f = lgg(alloc, pairs, &f1.f, &f2.f);
+                Expr::App(Box::new(App {
+                    id: get_id(&f),
+                    loc: f1.loc,
+                    arg: lgg(alloc, pairs, &f1.arg, &f2.arg),
+                    f,
+                }))
+            }
+            _ => {
+                let id = match pairs.iter().find(|(_, x, y)| x == a && y == b) {
+                    Some((id, ..)) => id.clone(),
+                    None => {
+                        let id = alloc.fresh("g");
+                        pairs.push((id.clone(), a.clone(), b.clone()));
+                        id
+                    }
+                };
+                Expr::Var { id, loc: a.loc() }
+            }
+        },
+    })
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::error;
+use rhokell::{Alloc, AttrArg, DisplayWithAlloc};
+
+// the attributes of the first clause of `name`, and the warnings
+fn attrs(program: &str, name: &str) -> (Vec<(String, Vec<AttrArg>)>, Vec<String>) {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, program.into()).unwrap();
+    let def = rules
+        .values()
+        .flatten()
+        .find(|def| alloc.get_string(&def.id) == name)
+        .unwrap();
+    let attrs = def
+        .attrs
+        .iter()
+        .map(|a| (alloc.get_string(&a.name).to_string(), a.args.clone()))
+        .collect();
+    let warnings = alloc.take_warnings().into_iter().map(|w| w.desc).collect();
+    (attrs, warnings)
+}
+
+#[test]
+fn doc_and_doc_comments() {
+    let (attrs, warnings) = attrs("## one more\n@doc \"inc\" (inc n) = (s n);", "inc");
+    assert_eq!(
+        attrs,
+        [
+            ("doc".into(), vec![AttrArg::Str("one more".into())]),
+            ("doc".into(), vec![AttrArg::Str("inc".into())]),
+        ]
+    );
+    assert!(warnings.is_empty(), "{warnings:?}");
+}
+
+#[test]
+fn deprecated_uses_warn() {
+    let p = "@deprecated \"use new\" (old) = (z);\n(new) = (z);\n(main) = (old);";
+    let (attrs, warnings) = attrs(p, "old");
+    assert_eq!(
+        attrs,
+        [("deprecated".into(), vec![AttrArg::Str("use new".into())])]
+    );
+    assert_eq!(warnings, ["`old` is deprecated: use new"]);
+}
+
+#[test]
+fn trace_keeps_the_result() {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, "@trace (inc n) = (s n);".into()).unwrap();
+    let mut e = rhokell::parse_expr(&mut alloc, "(inc (z))".into()).unwrap();
+    rhokell::apply(&rules, &mut e, &mut alloc);
+    assert_eq!(e.to_string(&alloc), "(s (z))");
+}
+
+#[test]
+fn unknown_attributes_warn() {
+    for name in ["memo", "strict", "fast"] {
+        let (attrs, warnings) = attrs(&format!("@{name} (f) = (z);"), "f");
+        assert_eq!(attrs, [(name.into(), vec![])]);
+        assert_eq!(warnings, [format!("unknown attribute `@{name}`")]);
+    }
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("@doc (f) = (z);"),
+        "wrong number of arguments for attribute `@doc`"
+    );
+    assert_eq!(
+        error("@trace \"why\" (f) = (z);"),
+        "wrong number of arguments for attribute `@trace`"
+    );
+    assert_eq!(
+        error("@deprecated #18446744073709551616 (f) = (z);"),
+        "attribute argument `#18446744073709551616` is not a natural number below 2^64"
+    );
+}
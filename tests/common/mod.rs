@@ -0,0 +1,20 @@
+// helpers shared by the test crates, not all of which use each of them
+#![allow(dead_code)]
+
+use rhokell::{Alloc, DisplayWithAlloc};
+
+// `expr` evaluated in `program`, as printed
+pub fn eval(program: &str, expr: &str) -> String {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, program.into()).unwrap();
+    let mut e = rhokell::parse_expr(&mut alloc, expr.into()).unwrap();
+    rhokell::apply(&rules, &mut e, &mut alloc);
+    e.to_string(&alloc)
+}
+
+// the description of the error `program` fails to parse with
+pub fn error(program: &str) -> String {
+    rhokell::parse(&mut Alloc::new(), program.into())
+        .unwrap_err()
+        .desc
+}
@@ -0,0 +1,39 @@
+use std::{fs, path::Path};
+
+use rhokell::{cst, Alloc};
+
+// every `.rhk` file below `dir`, recursively
+fn sources(dir: &Path, out: &mut Vec<std::path::PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            sources(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rhk") {
+            out.push(path);
+        }
+    }
+}
+
+#[test]
+fn round_trip_examples() {
+    let mut paths = Vec::new();
+    sources(Path::new("examples"), &mut paths);
+    sources(Path::new("std"), &mut paths);
+    assert!(!paths.is_empty());
+    for path in paths {
+        let src = fs::read_to_string(&path).unwrap();
+        let cst = cst::parse(&mut Alloc::new(), &src, 0)
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e.desc));
+        assert_eq!(cst.to_string(), src, "{}", path.display());
+    }
+}
+
+#[test]
+fn round_trip_trivia() {
+    let src = "## doc\n(f #| a #| nested |# comment |#\n  |a b|) = [(g) \"s\" 'c' #3];  # end\n(";
+    let cst = cst::parse(&mut Alloc::new(), src, 0).unwrap();
+    assert_eq!(cst.to_string(), src);
+    assert_eq!(cst.eof.trivia, "");
+    let last = cst.nodes.last().unwrap();
+    assert!(matches!(last, cst::Node::Group(g) if g.close.is_none()));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{error, eval};
+use rhokell::{Alloc, DisplayWithAlloc};
+
+#[test]
+fn more_specific_first() {
+    let p = "pragma dispatch specific;\n(f x) = (any);\n(f (s n)) = (succ);";
+    assert_eq!(eval(p, "(f (s (z)))"), "(succ)");
+    assert_eq!(eval(p, "(f (z))"), "(any)");
+}
+
+#[test]
+fn as_patterns_rank_by_their_pattern() {
+    let p = "pragma dispatch specific;\n(f x) = (any);\n(f y@(s n)) = (succ y);";
+    assert_eq!(eval(p, "(f (s (z)))"), "(succ (s (z)))");
+    assert_eq!(eval(p, "(f (z))"), "(any)");
+}
+
+#[test]
+fn guarded_clauses_win_ties() {
+    let p = "pragma dispatch specific;
+(g x) = (plain);
+(g x) = (guarded) if (p x) => (t);
+(p (z)) = (t);";
+    assert_eq!(eval(p, "(g (z))"), "(guarded)");
+    assert_eq!(eval(p, "(g (s (z)))"), "(plain)");
+}
+
+#[test]
+fn ordered_by_default() {
+    let p = "(f x) = (any);\n(f (s n)) = (succ);";
+    assert_eq!(eval(p, "(f (s (z)))"), "(any)");
+}
+
+#[test]
+fn ambiguous_overlap() {
+    let p = "pragma dispatch specific;\n(h x (z)) = (a);\n(h (z) y) = (b);";
+    assert!(error(p).contains("neither is more specific"));
+}
+
+#[test]
+fn pragma_applies_to_its_own_file() {
+    let mut alloc = Alloc::new();
+    let path = std::path::Path::new("tests/data/dispatch/main.rhk");
+    let rules = rhokell::parse_file(&mut alloc, path).unwrap();
+    let mut eval = |expr: &str| {
+        let mut e = rhokell::parse_expr(&mut alloc, expr.into()).unwrap();
+        rhokell::apply(&rules, &mut e, &mut alloc);
+        e.to_string(&alloc)
+    };
+    assert_eq!(eval("(f (s (z)))"), "(any)");
+    assert_eq!(eval("(g (s (z)))"), "(succ)");
+}
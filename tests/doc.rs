@@ -0,0 +1,71 @@
+use rhokell::{doc, Alloc};
+
+const SRC: &str =
+    "## adds one\n(inc n) = (s n);\n(add (z) m) = m;\n(add (s n) m) = (inc (add n m));\n";
+
+#[test]
+fn markdown_entries() {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, SRC.into()).unwrap();
+    let md = doc::markdown(&alloc, &rules, "nat");
+    assert!(md.starts_with("# nat\n"));
+    assert!(md.contains("### `inc`\n\n*arity 1*\n\nadds one\n"), "{md}");
+    assert!(md.contains("(add (s n) m) = (inc (add n m));\n"), "{md}");
+    assert!(md.contains("calls: [`inc`](#f-inc)"), "{md}");
+    assert!(md.contains("called by: [`add`](#f-add)"), "{md}");
+    assert!(md.find("`inc`").unwrap() < md.find("`add`").unwrap());
+}
+
+#[test]
+fn html_links_and_highlights() {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, SRC.into()).unwrap();
+    let html = doc::html(&alloc, &rules, "nat");
+    assert!(html.contains("<section id=\"f-add\">"), "{html}");
+    assert!(
+        html.contains("<a class=\"fun\" href=\"#f-inc\">inc</a>"),
+        "{html}"
+    );
+    assert!(html.contains("<span class=\"var\">m</span>"), "{html}");
+    assert!(html.contains("<span class=\"fun\">s</span>"), "{html}");
+}
+
+#[test]
+fn clauses_as_written() {
+    let src = "(twice f x) = (go x) where { (go y) = (f (f y)); };
+(pairs l) = (map \\x -> (pair x x) l);\n";
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, src.into()).unwrap();
+    let md = doc::markdown(&alloc, &rules, "local");
+    assert!(
+        md.contains("(twice f x) = (go x) where { (go y) = (f (f y)); };\n"),
+        "{md}"
+    );
+    assert!(
+        md.contains("(pairs l) = (map \\x -> (pair x x) l);\n"),
+        "{md}"
+    );
+    assert!(!md.contains("h_0") && !md.contains("lambda_0"), "{md}");
+    let html = doc::html(&alloc, &rules, "local");
+    assert!(
+        !html.contains("lambda_0") && !html.contains("h_0"),
+        "{html}"
+    );
+    assert!(html.contains("<span class=\"key\">where</span>"), "{html}");
+    assert!(html.contains("<span class=\"var\">f</span>"), "{html}");
+}
+
+#[test]
+fn backticks_in_names() {
+    let src = "(|a```b| x) = x;\n(c) = (|a```b| (d));\n";
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, src.into()).unwrap();
+    let md = doc::markdown(&alloc, &rules, "`tick`");
+    assert!(md.starts_with("# \\`tick\\`\n"), "{md}");
+    assert!(md.contains("### ````a```b````\n"), "{md}");
+    assert!(md.contains("calls: [````a```b````](#"), "{md}");
+    assert!(
+        md.contains("\n````rhokell\n(c) = (|a```b| (d));\n````\n"),
+        "{md}"
+    );
+}
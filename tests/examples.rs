@@ -0,0 +1,34 @@
+use std::path::{Path, PathBuf};
+
+use rhokell::Alloc;
+
+fn rhk_files(dir: &Path, res: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rhk_files(&path, res);
+        } else if path.extension().is_some_and(|ext| ext == "rhk") {
+            res.push(path);
+        }
+    }
+}
+
+#[test]
+fn all_examples_load() {
+    let mut files = Vec::new();
+    rhk_files(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("examples"),
+        &mut files,
+    );
+    assert!(files.len() > 10, "{files:?}");
+    for file in files {
+        // the quine's code on its own, which starts after its data
+        if file.ends_with("quine/bare.rhk") {
+            continue;
+        }
+        let mut alloc = Alloc::new();
+        if let Err(err) = rhokell::parse_file(&mut alloc, &file) {
+            panic!("{}: {}", file.display(), err.desc);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::{fs, path::Path};
+
+use rhokell::{cst, format::format, Alloc};
+
+fn format_src(src: &str) -> String {
+    format(&mut Alloc::new(), src, 0).unwrap()
+}
+
+fn texts(src: &str) -> Vec<String> {
+    let cst = cst::parse(&mut Alloc::new(), src, 0).unwrap();
+    cst.tokens().iter().map(|t| t.text.clone()).collect()
+}
+
+fn examples() -> Vec<(String, String)> {
+    let mut res = Vec::new();
+    for dir in ["examples", "examples/quine", "std"] {
+        for entry in fs::read_dir(Path::new(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "rhk") {
+                let src = fs::read_to_string(&path).unwrap();
+                res.push((path.display().to_string(), src));
+            }
+        }
+    }
+    res
+}
+
+#[test]
+fn idempotent_on_examples() {
+    for (path, src) in examples() {
+        let once = format_src(&src);
+        assert_eq!(format_src(&once), once, "{path}");
+    }
+}
+
+#[test]
+fn keeps_tokens_of_examples() {
+    for (path, src) in examples() {
+        assert_eq!(texts(&format_src(&src)), texts(&src), "{path}");
+    }
+}
+
+#[test]
+fn aligns_and_spaces() {
+    let src = "(f  (z))=(z);\n(f (s   n) ) = ( s (f n));\n\n\n# next\n(g)=(g);";
+    assert_eq!(
+        format_src(src),
+        "(f (z))   = (z);\n(f (s n)) = (s (f n));\n\n# next\n(g) = (g);\n"
+    );
+}
+
+#[test]
+fn breaks_long_items() {
+    let args = "(argument x) ".repeat(10);
+    let src = format!("(f x) = (g {args}) if (p x) => (t);");
+    let out = format_src(&src);
+    assert!(out.lines().all(|l| l.len() <= 100), "{out}");
+    assert!(out.starts_with("(f x) =\n    (g (argument x)"), "{out}");
+    assert!(out.contains("\n      (argument x)"), "{out}");
+    assert!(out.contains("\n    if (p x) => (t);"), "{out}");
+    assert_eq!(format_src(&out), out);
+}
+
+#[test]
+fn keeps_comments() {
+    let src = "## doc\n@memo (f) = (g # why\n (h)); # trailing\n#| block |#\n";
+    let out = format_src(src);
+    for comment in ["## doc", "# why", "# trailing", "#| block |#"] {
+        assert!(out.contains(comment), "{out}");
+    }
+    assert_eq!(format_src(&out), out);
+
+    // a comment after the last definition in braces stays on its line
+    let src = "(f x) = (m) where {\n    (m) = (k); # after m\n    # own line\n};\n";
+    let out = format_src(src);
+    assert!(out.contains("\n        (m) = (k); # after m\n"), "{out}");
+    assert!(out.contains("\n        # own line\n    };"), "{out}");
+    assert_eq!(format_src(&out), out);
+}
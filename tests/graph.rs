@@ -0,0 +1,42 @@
+use rhokell::{graph::CallGraph, Alloc};
+
+const SRC: &str = "(even (z)) = (true);
+(even (s n)) = (odd n);
+(odd (z)) = (false);
+(odd (s n)) = (even n);
+(dead) = (even (z));
+(main) = (odd (s (z))) where { (go x) = (main); };
+";
+
+fn graph() -> CallGraph {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, SRC.into()).unwrap();
+    CallGraph::new(&alloc, &rules)
+}
+
+#[test]
+fn mutual_recursion_and_reachability() {
+    let graph = graph();
+    assert_eq!(graph.names, ["even", "odd", "dead", "main"]);
+    assert_eq!(graph.mutual_recursion(), [vec![0, 1]]);
+    assert_eq!(graph.unreachable("main"), Some(vec![2]));
+    assert_eq!(graph.unreachable("start"), None);
+}
+
+#[test]
+fn exports() {
+    let graph = graph();
+    let dot = graph.dot();
+    assert!(dot.starts_with("digraph calls {\n"), "{dot}");
+    assert!(dot.contains("    \"dead\" -> \"even\";\n"), "{dot}");
+    let json = graph.json("main");
+    assert!(
+        json.contains(r#"{"name":"odd","calls":["even"]}"#),
+        "{json}"
+    );
+    assert!(
+        json.contains(r#""mutual_recursion":[["even","odd"]]"#),
+        "{json}"
+    );
+    assert!(json.contains(r#""unreachable":["dead"]"#), "{json}");
+}
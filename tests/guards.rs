@@ -0,0 +1,49 @@
+mod common;
+
+use common::{error, eval};
+
+const GTE: &str = "(gte x (z)) = (t);
+(gte (z) (s y)) = (f);
+(gte (s x) (s y)) = (gte x y);
+";
+
+#[test]
+fn guard_picks_the_clause() {
+    let p = format!("{GTE}(max x y) = x if (gte x y) => (t);\n(max x y) = y;");
+    assert_eq!(eval(&p, "(max (s (s (z))) (s (z)))"), "(s (s (z)))");
+    assert_eq!(eval(&p, "(max (s (z)) (s (s (z))))"), "(s (s (z)))");
+}
+
+#[test]
+fn guards_bind_variables() {
+    let p = "(pred (s n)) = (just n);
+(pred (z)) = (none);
+(dec2 x) = (two m) if (pred x) => (just n) if (pred n) => (just m);
+(dec2 x) = (fewer);";
+    assert_eq!(eval(p, "(dec2 (s (s (s (z)))))"), "(two (s (z)))");
+    assert_eq!(eval(p, "(dec2 (s (z)))"), "(fewer)");
+}
+
+#[test]
+fn guards_see_reduced_arguments() {
+    let p = format!("{GTE}(big x) = (yes) if (gte x (s (s (z)))) => (t);\n(big x) = (no);\n(two) = (s (s (z)));");
+    assert_eq!(eval(&p, "(big (two))"), "(yes)");
+    assert_eq!(eval(&p, "(big (z))"), "(no)");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("(f x) = x if (g y) => (t);"),
+        "undefined variables: y"
+    );
+    assert_eq!(
+        error("(f x) = y if (g x) => (t);"),
+        "undefined variables: y"
+    );
+    // a variable bound by a later guard is not in scope in earlier ones
+    assert_eq!(
+        error("(f x) = x if (g y) => (t) if (h x) => y;"),
+        "undefined variables: y"
+    );
+}
@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+use rhokell::{Alloc, DisplayWithAlloc};
+
+fn data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/imports")
+        .join(name)
+}
+
+fn eval(file: &str, expr: &str) -> String {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse_file(&mut alloc, &data(file)).unwrap();
+    let mut e = rhokell::parse_expr(&mut alloc, expr.into()).unwrap();
+    rhokell::apply(&rules, &mut e, &mut alloc);
+    e.to_string(&alloc)
+}
+
+#[test]
+fn resolves_relative_to_the_importer() {
+    // `shared.rhk` is imported twice, along different relative paths
+    assert_eq!(eval("main.rhk", "(main)"), "(both (ok) (ok))");
+}
+
+#[test]
+fn keeps_clause_order() {
+    assert_eq!(eval("main.rhk", "(f (z))"), "(first)");
+    assert_eq!(eval("main.rhk", "(f (s (z)))"), "(from_a)");
+    assert_eq!(eval("main.rhk", "(f (s (s (z))))"), "(last)");
+}
+
+#[test]
+fn errors() {
+    let mut alloc = Alloc::new();
+    let err = rhokell::parse_file(&mut alloc, &data("cycle_a.rhk")).unwrap_err();
+    let cycle: Vec<_> = err
+        .desc
+        .strip_prefix("import cycle: ")
+        .unwrap()
+        .split(" -> ")
+        .map(|p| Path::new(p).file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(cycle, ["cycle_a.rhk", "cycle_b.rhk", "cycle_a.rhk"]);
+
+    let err = rhokell::parse_file(&mut alloc, &data("missing.rhk")).unwrap_err();
+    assert!(err.desc.starts_with("could not find `"), "{}", err.desc);
+    assert!(alloc
+        .get_file(err.loc.file)
+        .unwrap()
+        .ends_with("missing.rhk"));
+
+    // errors are located in the file they are in
+    let err = rhokell::parse_file(&mut alloc, &data("uses_broken.rhk")).unwrap_err();
+    assert_eq!((err.loc.line, err.loc.col), (2, 9));
+    assert!(alloc
+        .get_file(err.loc.file)
+        .unwrap()
+        .ends_with("sub/broken.rhk"));
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::path::Path;
+
+use common::{error, eval};
+use rhokell::{Alloc, DisplayWithAlloc, Style};
+
+const LISTS: &str = "infixr 5 ++ :>;
+(++ (nil) ys) = ys;
+(++ (cons x xs) ys) = (cons x (++ xs ys));
+(:> x xs) = (cons x xs);
+";
+
+#[test]
+fn precedence_and_associativity() {
+    let p = "infixl 6 + -;\ninfixl 7 *;\n(+ a b) = (plus a b);\n(- a b) = (minus a b);\n(* a b) = (times a b);
+(e) = ((one) + (two) * (three) - (four));";
+    assert_eq!(
+        eval(p, "(e)"),
+        "(minus (plus (one) (times (two) (three))) (four))"
+    );
+}
+
+#[test]
+fn numeral_operands() {
+    let p = "pragma numerals native;\ninfixl 6 +;\ninfixl 7 *;\n(e) = (#1 + #2 * #3);";
+    assert_eq!(eval(p, "(e)"), "#7");
+}
+
+#[test]
+fn string_char_and_list_operands() {
+    let p = format!(
+        "{LISTS}(s) = (\"ab\" ++ \"c\");\n(c) = ('a' :> \"bc\");\n(l) = ([(a)] ++ [(b)]);\n(abc) = \"abc\";\n(ab) = [(a) (b)];"
+    );
+    assert_eq!(eval(&p, "(s)"), eval(&p, "(abc)"));
+    assert_eq!(eval(&p, "(c)"), eval(&p, "(abc)"));
+    assert_eq!(eval(&p, "(l)"), eval(&p, "(ab)"));
+}
+
+#[test]
+fn fixities_stay_in_their_file() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/infix");
+    let mut alloc = Alloc::new();
+    // `main.rhk` redeclares the `-` of `ops.rhk` with another fixity
+    let rules = rhokell::parse_file(&mut alloc, &dir.join("main.rhk")).unwrap();
+    for (expr, res) in [
+        ("(d)", "(minus (minus (a) (b)) (c))"),
+        ("(e)", "(minus (a) (minus (b) (c)))"),
+    ] {
+        let mut e = rhokell::parse_expr(&mut alloc, expr.into()).unwrap();
+        rhokell::apply(&rules, &mut e, &mut alloc);
+        assert_eq!(e.to_string(&alloc), res);
+    }
+    // and without a declaration of its own `-` is no operator in a file
+    let err = rhokell::parse_file(&mut alloc, &dir.join("prefix.rhk")).unwrap_err();
+    assert!(err.desc.contains("undefined variables: -"), "{}", err.desc);
+    // nor in the expressions the REPL reads
+    let e = rhokell::parse_expr(&mut alloc, "(f - (b))".into()).unwrap();
+    assert_eq!(e.to_string(&alloc), "(f - (b))");
+}
+
+#[test]
+fn prints_infix() {
+    let mut alloc = Alloc::new();
+    rhokell::parse(&mut alloc, "infixl 6 +;\ninfixl 7 *;".into()).unwrap();
+    let e = rhokell::parse_expr(&mut alloc, "(+ a (* b c))".into()).unwrap();
+    let mut s = String::new();
+    let style = Style {
+        infix: true,
+        ..Style::default()
+    };
+    e.display_styled(&alloc, style, &mut s);
+    assert_eq!(s, "(a + b * c)");
+}
+
+#[test]
+fn errors() {
+    assert!(error("infixl 6 +;\n(e) = (#1 #2);").contains("cannot be applied"));
+    assert!(error("infixl 6 +;\n(e) = (\"a\" +);").contains("expected an operand"));
+    assert!(error("infixl 6 +;\ninfixr 6 +;").contains("conflicting fixity"));
+    assert!(error("infixl 10 +;").contains("precedence from 0 to 9"));
+}
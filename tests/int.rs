@@ -0,0 +1,114 @@
+mod common;
+
+use common::eval;
+use rhokell::{Alloc, Int};
+
+fn int(s: &str) -> Int {
+    Int::parse(s).unwrap()
+}
+
+#[test]
+fn parse_and_display() {
+    for s in [
+        "0",
+        "7",
+        "-7",
+        "4294967296",
+        "18446744073709551616",
+        "-123456789012345678901234567890",
+    ] {
+        assert_eq!(int(s).to_string(), s);
+    }
+    assert_eq!(int("-0").to_string(), "0");
+    assert_eq!(int("007").to_string(), "7");
+    assert!(Int::parse("").is_none() && Int::parse("-").is_none() && Int::parse("1x").is_none());
+}
+
+#[test]
+fn add_and_sub_carry_and_borrow() {
+    assert_eq!((&int("4294967295") + &int("1")).to_string(), "4294967296");
+    assert_eq!(
+        (&int("18446744073709551615") + &int("1")).to_string(),
+        "18446744073709551616"
+    );
+    assert_eq!(
+        (&int("18446744073709551616") - &int("1")).to_string(),
+        "18446744073709551615"
+    );
+    assert_eq!((&int("4294967296") - &int("4294967297")).to_string(), "-1");
+    assert_eq!((&int("-5") + &int("3")).to_string(), "-2");
+    assert_eq!((&int("-5") - &int("-5")).to_string(), "0");
+    assert!(!(&int("-5") - &int("-5")).is_negative());
+}
+
+#[test]
+fn mul_past_u64() {
+    let a = int("18446744073709551615");
+    assert_eq!(
+        (&a * &a).to_string(),
+        "340282366920938463426481119284349108225"
+    );
+    assert_eq!(
+        (&int("-3") * &int("4294967296")).to_string(),
+        "-12884901888"
+    );
+    assert!((&int("0") * &int("-3")).is_zero());
+}
+
+#[test]
+fn div_rem() {
+    let (q, r) = int("340282366920938463426481119284349108226")
+        .div_rem(&int("18446744073709551615"))
+        .unwrap();
+    assert_eq!(
+        (q.to_string(), r.to_string()),
+        ("18446744073709551615".into(), "1".into())
+    );
+    let (q, r) = int("-7").div_rem(&int("2")).unwrap();
+    assert_eq!((q.to_string(), r.to_string()), ("-3".into(), "-1".into()));
+    let (q, r) = int("100000000000000000000").div_rem(&int("7")).unwrap();
+    assert_eq!(
+        (q.to_string(), r.to_string()),
+        ("14285714285714285714".into(), "2".into())
+    );
+    assert!(int("1").div_rem(&int("0")).is_none());
+}
+
+#[test]
+fn conversions_and_order() {
+    assert_eq!(int("18446744073709551615").to_u64(), Some(u64::MAX));
+    assert_eq!(int("18446744073709551616").to_u64(), None);
+    assert_eq!(int("-1").to_u64(), None);
+    assert_eq!(Int::from(u64::MAX), int("18446744073709551615"));
+    assert!(int("-18446744073709551616") < int("-1"));
+    assert!(int("18446744073709551616") > int("18446744073709551615"));
+}
+
+#[test]
+fn native_literals_past_u64() {
+    let p = "pragma numerals native;
+(big) = #18446744073709551616;
+(neg) = (- #1 #3);
+(back) = #-2;
+(fact n) = #1 if (eq n #0) => (t);
+(fact n) = (* n (fact (- n #1)));
+(fact25) = (fact #25);
+(third) = (div (big) #-3);";
+    assert_eq!(eval(p, "(+ (big) (big))"), "#36893488147419103232");
+    assert_eq!(eval(p, "(neg)"), "#-2");
+    assert_eq!(eval(p, "(eq (neg) (back))"), "(t)");
+    assert_eq!(eval(p, "(fact25)"), "#15511210043330985984000000");
+    assert_eq!(eval(p, "(third)"), "#-6148914691236517205");
+}
+
+#[test]
+fn literal_errors() {
+    let err = |p: &str| {
+        rhokell::parse(&mut Alloc::new(), p.into())
+            .unwrap_err()
+            .desc
+    };
+    assert!(err("(a) = #18446744073709551616;").contains("pragma numerals native"));
+    assert!(err("(a) = #-1;").contains("pragma numerals native"));
+    assert!(err("(a) = #1x;").contains("invalid numeric literal `#1x`"));
+}
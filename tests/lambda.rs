@@ -0,0 +1,60 @@
+mod common;
+
+use common::{error, eval};
+
+const LIST: &str = "(cons (z) (cons (s (z)) (nil)))";
+
+#[test]
+fn lambda_with_map() {
+    let p = "import \"std:lists\";\n(incs l) = (lists.map \\x -> (s x) l);";
+    assert_eq!(
+        eval(p, &format!("(incs {LIST})")),
+        "(cons (s (z)) (cons (s (s (z))) (nil)))"
+    );
+}
+
+#[test]
+fn lambda_captures_variables() {
+    let p = "import \"std:lists\";\n(tag t l) = (lists.map \\x -> (pair t x) l);";
+    assert_eq!(
+        eval(p, &format!("(tag (a) {LIST})")),
+        "(cons (pair (a) (z)) (cons (pair (a) (s (z))) (nil)))"
+    );
+}
+
+#[test]
+fn lambda_captures_function_arguments() {
+    let p = "import \"std:lists\";\n(apply f l) = (lists.map \\x -> (f x) l);";
+    assert_eq!(
+        eval(p, &format!("(apply (h) {LIST})")),
+        "(cons (h (z)) (cons (h (s (z))) (nil)))"
+    );
+}
+
+#[test]
+fn lambda_applied_directly() {
+    assert_eq!(
+        eval("(a) = (\\x y -> (pair y x) (l) (r));", "(a)"),
+        "(pair (r) (l))"
+    );
+}
+
+#[test]
+fn rule_blocks() {
+    let p = "import \"std:lists\";
+(zeros l) = (lists.filter { (_ (z)) = (t); (_ x) = (f); } l);
+(pick f l) = (lists.map { (_ (z)) = (f (zero)); (_ x) = (f x); } l);";
+    assert_eq!(eval(p, &format!("(zeros {LIST})")), "(cons (z) (nil))");
+    assert_eq!(
+        eval(p, &format!("(pick (h) {LIST})")),
+        "(cons (h (zero)) (cons (h (s (z))) (nil)))"
+    );
+}
+
+#[test]
+fn errors() {
+    assert!(error("(a) = \\ -> (b);").contains("at least one parameter"));
+    assert!(error("(a) = { (g x) = x; };").contains("must be named `_`"));
+    assert!(error("(a) = { };").contains("at least one rule"));
+    assert!(error("(a) = \\x -> y;").contains("undefined variables: y"));
+}
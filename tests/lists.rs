@@ -0,0 +1,40 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn desugar_to_cons_cells() {
+    assert_eq!(eval("", "[]"), "(nil)");
+    assert_eq!(
+        eval("", "[(a) (b) (c)]"),
+        "(cons (a) (cons (b) (cons (c) (nil))))"
+    );
+    assert_eq!(eval("", "[(a) | (rest)]"), "(cons (a) (rest))");
+}
+
+#[test]
+fn match_in_patterns() {
+    let p = "(len []) = (z);\n(len [_ | rest]) = (s (len rest));
+(swap2 [a b | rest]) = [b a | rest];";
+    assert_eq!(eval(p, "(len [(a) (b) (c)])"), "(s (s (s (z))))");
+    assert_eq!(
+        eval(p, "(swap2 [(a) (b) (c)])"),
+        "(cons (b) (cons (a) (cons (c) (nil))))"
+    );
+}
+
+#[test]
+fn constructors_from_pragma() {
+    let p = "pragma list Cons Nil;\n(first [x | _]) = x;\n(all) = [(a) (b)];";
+    assert_eq!(eval(p, "(first (Cons (a) (Nil)))"), "(a)");
+    assert_eq!(eval(p, "(all)"), "(Cons (a) (Cons (b) (Nil)))");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(error("pragma list Cons;"), "unknown pragma `list Cons`");
+    assert_eq!(
+        error("(f) = [(a) | (b) (c)];"),
+        "expected token `]`, found token `(`"
+    );
+}
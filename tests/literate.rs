@@ -0,0 +1,27 @@
+use std::path::{Path, PathBuf};
+
+use rhokell::{Alloc, DisplayWithAlloc};
+
+fn data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/literate")
+        .join(name)
+}
+
+#[test]
+fn runs_tagged_blocks() {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse_file(&mut alloc, &data("tutorial.rhk.md")).unwrap();
+    let mut e = rhokell::parse_expr(&mut alloc, "(main)".into()).unwrap();
+    rhokell::apply(&rules, &mut e, &mut alloc);
+    assert_eq!(e.to_string(&alloc), "(s (s (s (s (z)))))");
+}
+
+#[test]
+fn errors_point_into_the_markdown() {
+    let mut alloc = Alloc::new();
+    let err = rhokell::parse_file(&mut alloc, &data("broken.rhk.md")).unwrap_err();
+    assert_eq!((err.loc.line, err.loc.col), (10, 9));
+    let file = alloc.get_file(err.loc.file).unwrap();
+    assert!(file.ends_with("broken.rhk.md"), "{}", file.display());
+}
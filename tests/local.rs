@@ -0,0 +1,59 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn where_captures_pattern_variables() {
+    let p = "(add n m) = (go m) where {
+    (go (z)) = n;
+    (go (s k)) = (s (go k));
+};";
+    assert_eq!(eval(p, "(add (s (z)) (s (s (z))))"), "(s (s (s (z))))");
+}
+
+#[test]
+fn where_functions_call_each_other() {
+    let p = "(parity n) = (even n) where {
+    (even (z)) = (t);
+    (even (s k)) = (odd k);
+    (odd (z)) = (f);
+    (odd (s k)) = (even k);
+};";
+    assert_eq!(eval(p, "(parity (s (s (s (z)))))"), "(f)");
+}
+
+#[test]
+fn where_captures_higher_order_arguments() {
+    let p = "(twice f x) = (h (h x)) where { (h y) = (f y); };";
+    assert_eq!(eval(p, "(twice (g) (z))"), "(g (g (z)))");
+    assert_eq!(eval(p, "(twice (s) (z))"), "(s (s (z)))");
+}
+
+#[test]
+fn let_binds_patterns() {
+    let p = "(swap p) = let (pair a b) = p in (pair b a);
+(sum x) = let y = (s x) in let z = (s y) in (tuple x y z);";
+    assert_eq!(eval(p, "(swap (pair (l) (r)))"), "(pair (r) (l))");
+    assert_eq!(eval(p, "(sum (z))"), "(tuple (z) (s (z)) (s (s (z))))");
+}
+
+#[test]
+fn let_captures_higher_order_arguments() {
+    assert_eq!(
+        eval("(ap f x) = let y = (f x) in (f y);", "(ap (g) (z))"),
+        "(g (g (z)))"
+    );
+}
+
+#[test]
+fn local_names_do_not_leak() {
+    let p = "(a) = (h) where { (h) = (inner); };\n(h) = (outer);";
+    assert_eq!(eval(p, "(a)"), "(inner)");
+    assert_eq!(eval(p, "(h)"), "(outer)");
+}
+
+#[test]
+fn errors() {
+    assert!(error("(a x) = let y = x (y);").contains("expected `in`"));
+    assert!(error("(a) = y where { (h) = (z); };").contains("undefined variables: y"));
+}
@@ -0,0 +1,260 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+const URI: &str = "file:///tmp/lsp-test.rhk";
+
+struct Client {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Client {
+    fn send(&mut self, msg: &str) {
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{msg}", msg.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> String {
+        let mut len = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(n) = line.strip_prefix("Content-Length:") {
+                len = n.trim().parse().unwrap();
+            }
+        }
+        let mut buf = vec![0; len];
+        self.stdout.read_exact(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    // sends request `id` and waits for its response
+    fn request(&mut self, id: u64, method: &str, params: &str) -> String {
+        self.send(&format!(
+            r#"{{"jsonrpc":"2.0","id":{id},"method":"{method}","params":{params}}}"#
+        ));
+        loop {
+            let msg = self.receive();
+            if msg.contains(&format!(r#""id":{id}"#)) {
+                return msg;
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: &str) {
+        self.send(&format!(
+            r#"{{"jsonrpc":"2.0","method":"{method}","params":{params}}}"#
+        ));
+    }
+
+    fn at(&mut self, id: u64, method: &str, line: u64, character: u64) -> String {
+        self.at_uri(id, method, URI, line, character, true)
+    }
+
+    fn at_uri(
+        &mut self,
+        id: u64,
+        method: &str,
+        uri: &str,
+        line: u64,
+        character: u64,
+        declaration: bool,
+    ) -> String {
+        let params = format!(
+            r#"{{"textDocument":{{"uri":"{uri}"}},"position":{{"line":{line},"character":{character}}},"context":{{"includeDeclaration":{declaration}}}}}"#
+        );
+        self.request(id, method, &params)
+    }
+
+    fn open(&mut self, uri: &str, text: &str) -> String {
+        self.notify(
+            "textDocument/didOpen",
+            &format!(
+                r#"{{"textDocument":{{"uri":"{uri}","languageId":"rhokell","version":1,"text":"{text}"}}}}"#
+            ),
+        );
+        self.receive()
+    }
+}
+
+fn start() -> (Child, Client) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rhokell"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut client = Client {
+        stdin: child.stdin.take().unwrap(),
+        stdout: BufReader::new(child.stdout.take().unwrap()),
+    };
+    client.request(1, "initialize", r#"{"capabilities":{}}"#);
+    client.notify("initialized", "{}");
+    (child, client)
+}
+
+fn quote(src: &str) -> String {
+    src.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[test]
+fn scripted_session() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rhokell"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut client = Client {
+        stdin: child.stdin.take().unwrap(),
+        stdout: BufReader::new(child.stdout.take().unwrap()),
+    };
+
+    let init = client.request(1, "initialize", r#"{"capabilities":{}}"#);
+    assert!(init.contains(r#""hoverProvider":true"#), "{init}");
+    client.notify("initialized", "{}");
+
+    let broken = "(inc n) = ;\n";
+    client.notify(
+        "textDocument/didOpen",
+        &format!(
+            r#"{{"textDocument":{{"uri":"{URI}","languageId":"rhokell","version":1,"text":"{}"}}}}"#,
+            quote(broken)
+        ),
+    );
+    let diags = client.receive();
+    assert!(diags.contains("publishDiagnostics"), "{diags}");
+    assert!(diags.contains(r#""severity":1"#), "{diags}");
+
+    let src = "## adds one\n(inc n) = (s n);\n(main) = (inc (inc (z)));\n";
+    client.notify(
+        "textDocument/didChange",
+        &format!(
+            r#"{{"textDocument":{{"uri":"{URI}","version":2}},"contentChanges":[{{"text":"{}"}}]}}"#,
+            quote(src)
+        ),
+    );
+    let diags = client.receive();
+    assert!(diags.contains(r#""diagnostics":[]"#), "{diags}");
+
+    let def = client.at(2, "textDocument/definition", 2, 11);
+    assert!(def.contains(URI), "{def}");
+    assert!(def.contains(r#""start":{"line":1,"#), "{def}");
+
+    let refs = client.at(3, "textDocument/references", 2, 11);
+    assert_eq!(refs.matches(URI).count(), 3, "{refs}");
+
+    let hover = client.at(4, "textDocument/hover", 2, 11);
+    assert!(hover.contains("adds one"), "{hover}");
+    assert!(hover.contains("(inc n) = (s n);"), "{hover}");
+
+    let completion = client.at(5, "textDocument/completion", 2, 11);
+    assert!(
+        completion.contains(r#""label":"inc","kind":3"#),
+        "{completion}"
+    );
+    assert!(!completion.contains(r#""label":"main""#), "{completion}");
+
+    let unknown = client.request(6, "workspace/unknown", "{}");
+    assert!(unknown.contains("-32601"), "{unknown}");
+
+    let shutdown = client.request(7, "shutdown", "null");
+    assert!(shutdown.contains(r#""result":null"#), "{shutdown}");
+    client.notify("exit", "null");
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn resolves_symbols() {
+    let (mut child, mut client) = start();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/lsp");
+    let main = format!("file://{dir}/main.rhk");
+    let lib = format!("file://{dir}/lib.rhk");
+
+    // `lib.rhk` is only on disk. the emoji takes two UTF-16 code units, and
+    // is sent as a surrogate pair
+    let src = "import \"lib.rhk\";\nimport \"std:bool\";\n(inc inc) = (lib.inc inc);
+(main) = (pair \"\u{1F600}\" (inc (lib.twice (z))) (bool.not (t)));\n";
+    let src = quote(src).replace('\u{1F600}', "\\ud83d\\ude00");
+    let diags = client.open(&main, &src);
+    assert!(diags.contains(r#""diagnostics":[]"#), "{diags}");
+
+    // the variable `inc` and `lib.inc` are not uses of `inc`
+    let refs = client.at_uri(2, "textDocument/references", &main, 2, 2, true);
+    assert_eq!(refs.matches("\"uri\"").count(), 2, "{refs}");
+    assert!(
+        refs.contains(r#""start":{"line":2,"character":1}"#),
+        "{refs}"
+    );
+    assert!(
+        refs.contains(r#""start":{"line":3,"character":21},"end":{"line":3,"character":24}"#),
+        "{refs}"
+    );
+    let refs = client.at_uri(3, "textDocument/references", &main, 2, 2, false);
+    assert_eq!(refs.matches("\"uri\"").count(), 1, "{refs}");
+
+    // `lib.inc`, used and defined in the imported file, not open
+    let refs = client.at_uri(4, "textDocument/references", &main, 2, 15, true);
+    assert_eq!(refs.matches(&main).count(), 1, "{refs}");
+    assert_eq!(refs.matches(&lib).count(), 3, "{refs}");
+    assert!(
+        refs.contains(r#""start":{"line":2,"character":13}"#),
+        "{refs}"
+    );
+
+    // the std module has no file to point at
+    let def = client.at_uri(5, "textDocument/definition", &main, 3, 46, true);
+    assert!(def.contains(r#""result":[]"#), "{def}");
+    let def = client.at_uri(6, "textDocument/definition", &main, 3, 30, true);
+    assert!(def.contains(&lib), "{def}");
+
+    let hover = client.at_uri(7, "textDocument/hover", &main, 3, 2, true);
+    assert!(hover.contains("\u{1F600}"), "{hover}");
+
+    client.send("{\"jsonrpc\":\"2.0\",\"id\":8,");
+    let error = client.receive();
+    assert!(error.contains("-32700"), "{error}");
+    // a key must start with a quote
+    client.send(r#"{"jsonrpc":"2.0","id":9,"method":"shutdown",params":null}"#);
+    let error = client.receive();
+    assert!(error.contains("-32700"), "{error}");
+
+    client.request(10, "shutdown", "null");
+    client.notify("exit", "null");
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn quoted_and_local_functions() {
+    let (mut child, mut client) = start();
+    let src = "(|a b| x) = x;\n(twice f x) = (go x) where { (go y) = (f (f y)); };
+(main) = (|a b| (twice (g) (z)));\n(g x) = x;\n";
+    let diags = client.open(URI, &quote(src));
+    assert!(diags.contains(r#""diagnostics":[]"#), "{diags}");
+
+    let def = client.at(2, "textDocument/definition", 2, 11);
+    assert!(def.contains(r#""start":{"line":0,"character":1}"#), "{def}");
+    let hover = client.at(3, "textDocument/hover", 2, 11);
+    assert!(hover.contains("(|a b| x) = x;"), "{hover}");
+
+    // `go` is lifted out of `twice` under another name
+    let def = client.at(4, "textDocument/definition", 1, 16);
+    assert!(
+        def.contains(r#""start":{"line":1,"character":30}"#),
+        "{def}"
+    );
+    let hover = client.at(5, "textDocument/hover", 1, 16);
+    assert!(hover.contains("where { (go y) = (f (f y)); }"), "{hover}");
+
+    client.request(6, "shutdown", "null");
+    client.notify("exit", "null");
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::path::Path;
+
+use common::{error, eval};
+use rhokell::{Alloc, DisplayWithAlloc};
+
+#[test]
+fn expands_uses() {
+    let p = "macro (twice f x) => (f (f x));
+(main) = (twice (s) (z));";
+    assert_eq!(eval(p, "(main)"), "(s (s (z)))");
+}
+
+#[test]
+fn first_matching_rule() {
+    let p = "macro (swap (pair a b)) => (pair b a);
+macro (swap x) => (x);
+(one) = (swap (pair (z) (s (z))));
+(two) = (swap (z));";
+    assert_eq!(eval(p, "(one)"), "(pair (s (z)) (z))");
+    assert_eq!(eval(p, "(two)"), "(z)");
+}
+
+#[test]
+fn templates_use_other_macros() {
+    let p = "macro (twice f x) => (f (f x));
+macro (four f x) => (twice f (twice f x));
+(main) = (four (s) (z));";
+    assert_eq!(eval(p, "(main)"), "(s (s (s (s (z)))))");
+}
+
+#[test]
+fn expands_in_patterns() {
+    let p = "macro (two) => (s (s (z)));
+(is_two (two)) = (t);
+(is_two n) = (f);";
+    assert_eq!(eval(p, "(is_two (s (s (z))))"), "(t)");
+    assert_eq!(eval(p, "(is_two (s (z)))"), "(f)");
+}
+
+#[test]
+fn not_seen_by_importers() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/macros/main.rhk");
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse_file(&mut alloc, &path).unwrap();
+    let mut e = rhokell::parse_expr(&mut alloc, "(main)".into()).unwrap();
+    rhokell::apply(&rules, &mut e, &mut alloc);
+    assert_eq!(e.to_string(&alloc), "(twice (s) (s (s (s (z)))))");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("macro (twice f x) => (f (f x));\n(main) = (twice (z));"),
+        "no rule of macro `twice` matches this use"
+    );
+    assert!(
+        error("macro (loop x) => (loop (s x));\n(main) = (loop (z));").contains("nested more than"),
+    );
+    assert_eq!(
+        error("macro x => (z);"),
+        "a macro must be named by the function symbol it is applied to"
+    );
+    assert_eq!(
+        error(r"macro (k x) => (\y -> x);"),
+        "anonymous functions are not allowed in macros"
+    );
+}
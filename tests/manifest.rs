@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use rhokell::{Alloc, Manifest};
+
+fn parse(src: &str) -> Result<Manifest, String> {
+    Manifest::parse(&mut Alloc::new(), src, Path::new("/p/rhokell.toml")).map_err(|e| e.desc)
+}
+
+#[test]
+fn defaults() {
+    let m = parse("[project]\n").unwrap();
+    assert_eq!(m.entry, Path::new("/p/main.rhk"));
+    assert_eq!(m.main, "(main)");
+    assert!(m.src.is_empty() && m.lib.is_empty() && !m.prelude);
+}
+
+#[test]
+fn all_keys() {
+    let m = parse(
+        r##"# a project
+[project]
+entry = "app/start.rhk"  # the entry file
+main = "(run \"#1\")"
+src = ["app", "shared,with comma", ]
+lib = []
+prelude = true
+"##,
+    )
+    .unwrap();
+    assert_eq!(m.entry, Path::new("/p/app/start.rhk"));
+    assert_eq!(m.main, "(run \"#1\")");
+    assert_eq!(
+        m.src,
+        [Path::new("/p/app"), Path::new("/p/shared,with comma")]
+    );
+    assert!(m.lib.is_empty());
+    assert!(m.prelude);
+    assert_eq!(m.search_path(), m.src);
+}
+
+#[test]
+fn rejects_unknown_keys_and_sections() {
+    let err = parse("[project]\nentyr = \"main.rhk\"\n").unwrap_err();
+    assert!(err.contains("unknown key `entyr`"), "{err}");
+    let err = parse("[project]\n[dependencies]\n").unwrap_err();
+    assert!(err.contains("unknown section `[dependencies]`"), "{err}");
+    let err = parse("entry = \"main.rhk\"\n").unwrap_err();
+    assert!(err.contains("before the first key"), "{err}");
+}
+
+#[test]
+fn rejects_bad_values() {
+    let err = parse("[project]\nprelude = \"yes\"\n").unwrap_err();
+    assert!(err.contains("wrong type"), "{err}");
+    for value in [
+        "[\"a\" \"b\"]",
+        "[a]",
+        "\"open",
+        "[\"a\",,]",
+        "\"a\"b\"",
+        "[\"a\"b\"]",
+    ] {
+        let err = parse(&format!("[project]\nsrc = {value}\n")).unwrap_err();
+        assert!(err.contains("invalid value"), "{value}: {err}");
+    }
+    // a quote inside a string must be escaped
+    let err = parse("[project]\nmain = \"(ma\"in)\"\n").unwrap_err();
+    assert!(err.contains("invalid value"), "{err}");
+    assert!(parse("[project]\nmain\n")
+        .unwrap_err()
+        .contains("expected `key = value`"));
+}
+
+#[test]
+fn runs_a_project() {
+    use rhokell::DisplayWithAlloc;
+
+    let mut alloc = Alloc::new();
+    let path = Path::new("tests/data/project/rhokell.toml");
+    let manifest = Manifest::load(&mut alloc, path).unwrap();
+    let rules = rhokell::parse_project(&mut alloc, &manifest).unwrap();
+    let mut e = rhokell::parse_expr(&mut alloc, manifest.main).unwrap();
+    rhokell::apply(&rules, &mut e, &mut alloc);
+    assert_eq!(e.to_string(&alloc), "(hi (s (s (z))))");
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use common::error;
+use rhokell::{Alloc, DisplayWithAlloc};
+
+fn data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/modules")
+        .join(name)
+}
+
+#[test]
+fn qualified_names_do_not_collide() {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse_file(&mut alloc, &data("main.rhk")).unwrap();
+    let mut e = rhokell::parse_expr(&mut alloc, "(main)".into()).unwrap();
+    rhokell::apply(&rules, &mut e, &mut alloc);
+    assert_eq!(e.to_string(&alloc), "(pair (s (s (z))) (mine (z)))");
+    let names: Vec<_> = rules.keys().map(|id| alloc.get_string(id)).collect();
+    for name in ["counter.up", "counter.twice", "counter.do", "do", "main"] {
+        assert!(names.contains(&name), "{name} in {names:?}");
+    }
+}
+
+#[test]
+fn private_functions_are_hidden() {
+    let mut alloc = Alloc::new();
+    let err = rhokell::parse_file(&mut alloc, &data("peeks.rhk")).unwrap_err();
+    assert_eq!(err.desc, "`counter.do` is private to module `counter`");
+    assert_eq!((err.loc.line, err.loc.col), (3, 11));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("(f) = (g);\nmodule m;"),
+        "a module declaration must come before all definitions"
+    );
+    assert_eq!(
+        error("module m;\nmodule n;"),
+        "a module declaration must come before all definitions"
+    );
+}
@@ -0,0 +1,83 @@
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use rhokell::{Alloc, DisplayWithAlloc};
+
+fn solve(program: &str, goal: &str) -> Vec<String> {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(&mut alloc, program.into()).unwrap();
+    let (lhs, rhs) = rhokell::parse_goal(&mut alloc, goal.into()).unwrap();
+    rhokell::solve(&rules, &mut alloc, &lhs, &rhs, 8)
+        .iter()
+        .map(|s| s.to_string(&alloc))
+        .collect()
+}
+
+const ADD: &str = "(add (z) m) = m;\n(add (s n) m) = (s (add n m));\n";
+
+#[test]
+fn solves_for_an_argument() {
+    assert_eq!(
+        solve(ADD, "(add x (s (z))) = (s (s (s (z))))"),
+        ["x = (s (s (z)))"]
+    );
+}
+
+#[test]
+fn all_solutions_breadth_first() {
+    assert_eq!(
+        solve(ADD, "(add x y) = (s (s (z)))"),
+        [
+            "x = (z), y = (s (s (z)))",
+            "x = (s (z)), y = (s (z))",
+            "x = (s (s (z))), y = (z)",
+        ]
+    );
+}
+
+#[test]
+fn no_solution() {
+    assert!(solve(ADD, "(add x (s (z))) = (z)").is_empty());
+}
+
+#[test]
+fn guarded_rules() {
+    let p = "(half (z)) = (z);
+(half (s (s n))) = (s m) if (half n) => m;";
+    assert_eq!(solve(p, "(half x) = (s (z))"), ["x = (s (s (z)))"]);
+}
+
+#[test]
+fn goal_variables_are_not_captured() {
+    // the rule variables are renamed apart, to names no goal can spell
+    let p = "(add (z) n) = n;\n(add (s n) m) = (s (add n m));\n";
+    assert_eq!(
+        solve(p, "(add n'0 (s (z))) = (s (s (z)))"),
+        ["n'0 = (s (z))"]
+    );
+}
+
+#[test]
+fn repl_command() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/narrow/add.rhk");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rhokell"))
+        .arg("-r")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b":solve (add x (s (z))) = (s (s (s (z))))\n:solve (add x (s (z))) = (z)\nq\n")
+        .unwrap();
+    let out = child.wait_with_output().unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    assert!(out.contains("=> x = (s (s (z)))\n"), "{out}");
+    assert!(out.contains("=> no solutions found\n"), "{out}");
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{error, eval};
+use rhokell::{Alloc, Style};
+
+fn numbers(src: &str) -> String {
+    let mut alloc = Alloc::new();
+    let e = rhokell::parse_expr(&mut alloc, src.into()).unwrap();
+    let style = Style {
+        numbers: true,
+        ..Style::default()
+    };
+    let mut s = String::new();
+    e.display_styled(&alloc, style, &mut s);
+    s
+}
+
+#[test]
+fn unary_by_default() {
+    assert_eq!(eval("", "#0"), "(z)");
+    assert_eq!(eval("", "#3"), "(s (s (s (z))))");
+    let p = "(fact #0) = #1;\n(fact (s n)) = (mul (s n) (fact n));
+(mul #0 m) = #0;\n(mul (s n) m) = (add m (mul n m));
+(add #0 m) = m;\n(add (s n) m) = (s (add n m));";
+    assert_eq!(numbers(&eval(p, "(fact #3)")), "#6");
+}
+
+#[test]
+fn binary_and_custom_names() {
+    let p = "pragma numerals binary;\n(six) = #6;";
+    assert_eq!(eval(p, "(six)"), "(o (i (i (e))))");
+    let p = "pragma numerals unary succ zero;\n(two) = #2;";
+    assert_eq!(eval(p, "(two)"), "(succ (succ (zero)))");
+}
+
+#[test]
+fn print_back_as_numbers() {
+    assert_eq!(numbers("(pair (s (s (z))) (o (i (e))))"), "(pair #2 #2)");
+    assert_eq!(numbers("(s (x))"), "(s (x))");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("pragma numerals ternary;"),
+        "unknown pragma `numerals ternary`"
+    );
+    assert_eq!(
+        error("(f) = #-1;"),
+        "`#-1` is not a natural number below 2^64, which needs `pragma numerals native;`"
+    );
+    assert_eq!(error("(f) = #1x;"), "invalid numeric literal `#1x`");
+}
+
+#[test]
+fn hash_digit_is_no_comment() {
+    // `#` before a digit, or before `-` and a digit, starts a numeral
+    assert_eq!(
+        error("#1st case\n(f) = (g);"),
+        "invalid numeric literal `#1st`"
+    );
+    assert_eq!(
+        error("#-1 below zero\n(f) = (g);"),
+        "`#-1` is not a natural number below 2^64, which needs `pragma numerals native;`"
+    );
+    assert_eq!(eval("# 1st case\n(f) = #1;", "(f)"), "(s (z))");
+    assert_eq!(eval("#first case\n(f) = #1;", "(f)"), "(s (z))");
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn wildcards_are_independent() {
+    let p = "(second _ x _) = x;";
+    assert_eq!(eval(p, "(second (a) (b) (c))"), "(b)");
+    // a repeated variable still asks for equal terms
+    let p = "(same x x) = (t);\n(same _ _) = (f);";
+    assert_eq!(eval(p, "(same (a) (a))"), "(t)");
+    assert_eq!(eval(p, "(same (a) (b))"), "(f)");
+}
+
+#[test]
+fn as_patterns_bind_the_whole_term() {
+    let p = "(dup l@(cons a _)) = (cons a l);\n(dup l) = l;";
+    assert_eq!(
+        eval(p, "(dup (cons (x) (nil)))"),
+        "(cons (x) (cons (x) (nil)))"
+    );
+    assert_eq!(eval(p, "(dup (nil))"), "(nil)");
+}
+
+#[test]
+fn or_patterns_expand_to_clauses() {
+    let p = "(truthy (t) | (true) | (yes)) = (t);\n(truthy _) = (f);";
+    assert_eq!(eval(p, "(truthy (true))"), "(t)");
+    assert_eq!(eval(p, "(truthy (yes))"), "(t)");
+    assert_eq!(eval(p, "(truthy (no))"), "(f)");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(error("(f x) = _;"), "undefined variables: _");
+    assert_eq!(
+        error("(f x) = y@(g x);"),
+        "as-patterns are only allowed in patterns"
+    );
+    assert_eq!(
+        error("(f x) = (g (a) | (b));"),
+        "or-patterns are only allowed in patterns"
+    );
+}
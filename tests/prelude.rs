@@ -0,0 +1,65 @@
+mod common;
+
+use common::eval;
+use rhokell::Alloc;
+
+#[test]
+fn bool() {
+    let p = r#"import "std:bool";"#;
+    assert_eq!(eval(p, "(bool.if (t) (yes) (no))"), "(yes)");
+    assert_eq!(eval(p, "(bool.not (bool.and (t) (f)))"), "(t)");
+    assert_eq!(eval(p, "(bool.or (f) (f))"), "(f)");
+}
+
+#[test]
+fn peano() {
+    let p = r#"import "std:peano";"#;
+    assert_eq!(eval(p, "(peano.add (s (z)) (s (z)))"), "(s (s (z)))");
+    assert_eq!(
+        eval(p, "(peano.mul (s (s (z))) (s (s (z))))"),
+        "(s (s (s (s (z)))))"
+    );
+    assert_eq!(eval(p, "(peano.sub (s (z)) (s (s (z))))"), "(z)");
+    assert_eq!(
+        eval(p, "(peano.exp (s (s (z))) (s (s (z))))"),
+        "(s (s (s (s (z)))))"
+    );
+    assert_eq!(eval(p, "(peano.lt (s (z)) (s (s (z))))"), "(t)");
+    assert_eq!(eval(p, "(peano.eq (s (z)) (s (s (z))))"), "(f)");
+}
+
+#[test]
+fn lists() {
+    let p = r#"import "std:lists"; (odd (s (z))) = (t); (odd _) = (f);"#;
+    let l = "(cons (z) (cons (s (z)) (cons (s (s (z))) (nil))))";
+    assert_eq!(eval(p, &format!("(lists.length {l})")), "(s (s (s (z))))");
+    assert_eq!(
+        eval(p, &format!("(lists.reverse {l})")),
+        "(cons (s (s (z))) (cons (s (z)) (cons (z) (nil))))"
+    );
+    assert_eq!(
+        eval(p, &format!("(lists.filter (odd) {l})")),
+        "(cons (s (z)) (nil))"
+    );
+    assert_eq!(
+        eval(p, &format!("(lists.map (s) {l})")),
+        "(cons (s (z)) (cons (s (s (z))) (cons (s (s (s (z)))) (nil))))"
+    );
+    assert_eq!(eval(p, &format!("(lists.nth (s (z)) {l})")), "(s (z))");
+}
+
+#[test]
+fn private_helpers_are_hidden() {
+    let mut alloc = Alloc::new();
+    let p = r#"import "std:lists"; (main) = (lists.rev (nil) (nil));"#;
+    assert!(rhokell::parse(&mut alloc, p.into()).is_err());
+}
+
+#[test]
+fn prelude_imports_everything() {
+    let p = r#"import "std:prelude";"#;
+    assert_eq!(
+        eval(p, "(lists.length (cons (peano.add (z) (z)) (nil)))"),
+        "(s (z))"
+    );
+}
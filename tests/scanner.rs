@@ -0,0 +1,57 @@
+mod common;
+
+use common::{error, eval};
+use rhokell::{Alloc, AttrArg};
+
+#[test]
+fn quoted_symbols() {
+    let p = "(|hello world| x) = (|a(b)=c| x);";
+    assert_eq!(eval(p, "(|hello world| (z))"), "(|a(b)=c| (z))");
+    // the same name quoted or not is one symbol
+    assert_eq!(eval("(|f|) = (g);", "(f)"), "(g)");
+    // a lone `|` is a plain symbol
+    assert_eq!(eval("(| a b) = (a);", "(| (x) (y))"), "(x)");
+}
+
+#[test]
+fn reserved_quoted_symbols() {
+    for name in ["|twice;h_0|", "|let;|", "|#int|", "|@;|", "|@doc|"] {
+        let desc = error(&format!("(f) = ({name});"));
+        assert_eq!(
+            desc,
+            format!(
+                "`{name}` is reserved: quoted symbols cannot contain `;` or start with `#` or `@`"
+            )
+        );
+    }
+}
+
+#[test]
+fn block_comments_nest() {
+    let p = "(f) #| outer #| inner |# still outer |# = (g);";
+    assert_eq!(eval(p, "(f)"), "(g)");
+    assert_eq!(
+        error("(f) = (g); #| open #| nested |#"),
+        "unterminated block comment, expected `|#`"
+    );
+}
+
+#[test]
+fn doc_comments_attach_to_the_next_def() {
+    let mut alloc = Alloc::new();
+    let rules = rhokell::parse(
+        &mut alloc,
+        "## first\n## second\n(f) = (g);\n# plain\n(g) = (h);".into(),
+    )
+    .unwrap();
+    let docs = |name: &str| -> Vec<AttrArg> {
+        let def = rules
+            .values()
+            .flatten()
+            .find(|def| alloc.get_string(&def.id) == name)
+            .unwrap();
+        def.attrs.iter().flat_map(|a| a.args.clone()).collect()
+    };
+    assert_eq!(docs("f"), [AttrArg::Str("first\nsecond".into())]);
+    assert_eq!(docs("g"), []);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{error, eval};
+use rhokell::{Alloc, Style};
+
+fn styled(src: &str) -> String {
+    let mut alloc = Alloc::new();
+    let e = rhokell::parse_expr(&mut alloc, src.into()).unwrap();
+    let style = Style {
+        strings: true,
+        ..Style::default()
+    };
+    let mut s = String::new();
+    e.display_styled(&alloc, style, &mut s);
+    s
+}
+
+#[test]
+fn desugar_to_bytes() {
+    assert_eq!(eval("", "'H'"), "(byte (4) (8))");
+    assert_eq!(
+        eval("", "\"Hi\""),
+        "(cons (byte (4) (8)) (cons (byte (6) (9)) (nil)))"
+    );
+    assert_eq!(eval("", "'\\n'"), "(byte (0) (A))");
+    assert_eq!(eval("", "'\\x7F'"), "(byte (7) (F))");
+}
+
+#[test]
+fn match_in_patterns() {
+    let p = "(greet \"hi\") = \"hello\";\n(greet (cons 'h' rest)) = rest;";
+    assert_eq!(styled(&eval(p, "(greet \"hi\")")), "\"hello\"");
+    assert_eq!(styled(&eval(p, "(greet \"ho\")")), "\"o\"");
+}
+
+#[test]
+fn print_back_as_literals() {
+    assert_eq!(styled("\"a \\\"b\\\"\\n\""), "\"a \\\"b\\\"\\n\"");
+    assert_eq!(styled("(pair 'x' \"\")"), "(pair 'x' (nil))");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("(f) = 'ab';"),
+        "a character literal must be exactly one byte"
+    );
+    assert_eq!(error("(f) = \"a\\q\";"), "invalid escape `\\q`");
+    assert_eq!(error("(f) = \"abc;"), "unterminated literal, expected `\"`");
+}
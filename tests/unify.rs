@@ -0,0 +1,53 @@
+use rhokell::{unify, Alloc, DisplayWithAlloc, Expr};
+
+fn expr(alloc: &mut Alloc, src: &str) -> Expr {
+    rhokell::parse_expr(alloc, src.into()).unwrap()
+}
+
+#[test]
+fn unifies_both_sides() {
+    let mut alloc = Alloc::new();
+    let a = expr(&mut alloc, "(f x (g y))");
+    let b = expr(&mut alloc, "(f (g z) x)");
+    let s = unify::unify(&a, &b).unwrap();
+    assert_eq!(s.apply(&a), s.apply(&b));
+    assert_eq!(s.apply(&a).to_string(&alloc), "(f (g z) (g z))");
+}
+
+#[test]
+fn binds_variables_on_the_right() {
+    let mut alloc = Alloc::new();
+    let a = expr(&mut alloc, "(s (z))");
+    let b = expr(&mut alloc, "(s n)");
+    let s = unify::unify(&a, &b).unwrap();
+    let n = alloc.alloc_or_get("n");
+    assert_eq!(s.get(&n).unwrap().to_string(&alloc), "(z)");
+}
+
+#[test]
+fn occurs_check() {
+    let mut alloc = Alloc::new();
+    let a = expr(&mut alloc, "(f x)");
+    let b = expr(&mut alloc, "(f (s x))");
+    assert!(unify::unify(&a, &b).is_none());
+}
+
+#[test]
+fn clashing_symbols() {
+    let mut alloc = Alloc::new();
+    let a = expr(&mut alloc, "(cons x (nil))");
+    let b = expr(&mut alloc, "(cons y (cons z w))");
+    assert!(unify::unify(&a, &b).is_none());
+}
+
+#[test]
+fn generalizes() {
+    let mut alloc = Alloc::new();
+    let a = expr(&mut alloc, "(pair (a) (a) (c))");
+    let b = expr(&mut alloc, "(pair (b) (b) (c))");
+    let (g, sa, sb) = unify::generalize(&mut alloc, &a, &b);
+    assert_eq!(sa.apply(&g), a);
+    assert_eq!(sb.apply(&g), b);
+    assert_eq!(sa.len(), 1);
+    assert_eq!(g.to_string(&alloc), "(pair g_0 g_0 (c))");
+}